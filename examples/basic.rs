@@ -1,12 +1,15 @@
 extern crate flatbuffers;
 extern crate flatbuffers_retained;
 
+#[allow(clippy::all, mismatched_lifetime_syntaxes)]
 mod monster_generated;
 
 pub use monster_generated::my_game::sample::{
     root_as_monster, Color, Equipment, Monster, MonsterArgs, Vec3, Weapon, WeaponArgs,
 };
 
+flatbuffers_retained::retained_root!(Monster);
+
 fn main() -> Result<(), flatbuffers::InvalidFlatbuffer> {
     let mut builder = flatbuffers::FlatBufferBuilder::new();
     let orc = Monster::create(
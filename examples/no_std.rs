@@ -0,0 +1,49 @@
+//! Build configuration checking that the verify-once pattern works
+//! without the standard library, for example on firmware receiving
+//! flatbuffers over a serial link. Build it for a bare metal target with
+//!
+//! `cargo build --no-default-features --example no_std --target thumbv7em-none-eabihf`
+
+#![no_std]
+
+extern crate alloc;
+extern crate flatbuffers;
+extern crate flatbuffers_retained;
+
+use alloc::vec::Vec;
+
+#[allow(clippy::all, mismatched_lifetime_syntaxes)]
+#[path = "monster_generated.rs"]
+mod monster_generated;
+
+use monster_generated::my_game::sample::{Monster, MonsterArgs};
+
+flatbuffers_retained::retained_root!(Monster);
+flatbuffers_retained::retained_type! {
+    pub struct Frame(size_prefixed Monster) {
+        pub fn hp() -> i16 => hp;
+    }
+}
+
+/// Verify a size-prefixed frame once, then read it without re-verifying.
+pub fn receive(frame: Vec<u8>) -> Result<i16, flatbuffers_retained::Error> {
+    let monster = Frame::new(frame)?;
+    Ok(monster.hp())
+}
+
+/// Build a size-prefixed frame to send.
+pub fn send(hp: i16) -> Vec<u8> {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let monster = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp,
+            ..Default::default()
+        },
+    );
+    builder.finish_size_prefixed(monster, None);
+    let frame =
+        flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::from_builder(builder)
+            .expect("a freshly built frame verifies");
+    frame.trim().take()
+}
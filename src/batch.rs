@@ -0,0 +1,108 @@
+//! This module focuses on verifying many buffers at once, spreading
+//! the work across a thread pool when the `rayon` feature is enabled.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{RetainError, StableBytes};
+
+/// How a batch reacts to a buffer that fails verification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Verify every buffer and report every error.
+    CollectAll,
+    /// Stop verifying new buffers after the first error. Buffers that
+    /// were not verified are handed back in BatchReport::skipped.
+    /// With the `rayon` feature, buffers that were already being
+    /// verified on other threads still finish, so more than one error
+    /// may be reported.
+    FailFast,
+}
+
+/// The outcome of verifying a batch of buffers. Every entry carries the
+/// index of the buffer it came from, and each list is in index order.
+pub struct BatchReport<R, B> {
+    /// The buffers that verified.
+    pub retained: Vec<(usize, R)>,
+    /// The buffers that failed verification, with their errors.
+    pub errors: Vec<(usize, RetainError<B>)>,
+    /// The buffers that were not verified because of BatchMode::FailFast.
+    pub skipped: Vec<(usize, B)>,
+}
+
+impl<R, B> BatchReport<R, B> {
+    /// Return whether every buffer in the batch verified.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty() && self.skipped.is_empty()
+    }
+}
+
+impl<R: core::fmt::Debug, B: StableBytes> core::fmt::Debug for BatchReport<R, B> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let skipped: Vec<(usize, usize)> = self
+            .skipped
+            .iter()
+            .map(|(index, data)| (*index, data.as_ref().len()))
+            .collect();
+        f.debug_struct("BatchReport")
+            .field("retained", &self.retained)
+            .field("errors", &self.errors)
+            .field("skipped_lengths", &skipped)
+            .finish()
+    }
+}
+
+/// The outcome for a single buffer of a batch.
+enum Outcome<R, B> {
+    Retained(R),
+    Failed(RetainError<B>),
+    Skipped(B),
+}
+
+/// Run `retain` over every buffer, in parallel when the `rayon`
+/// feature is enabled, and gather the outcomes by index.
+pub(crate) fn retain_batch<R, B, F>(
+    buffers: Vec<B>,
+    mode: BatchMode,
+    retain: F,
+) -> BatchReport<R, B>
+where
+    R: Send,
+    B: StableBytes + Send,
+    F: Fn(B) -> Result<R, RetainError<B>> + Sync,
+{
+    let failed = AtomicBool::new(false);
+    let step = |(index, data): (usize, B)| {
+        if mode == BatchMode::FailFast && failed.load(Ordering::Relaxed) {
+            return (index, Outcome::Skipped(data));
+        }
+        match retain(data) {
+            Ok(retained) => (index, Outcome::Retained(retained)),
+            Err(err) => {
+                failed.store(true, Ordering::Relaxed);
+                (index, Outcome::Failed(err))
+            }
+        }
+    };
+    #[cfg(feature = "rayon")]
+    let outcomes: Vec<(usize, Outcome<R, B>)> = {
+        use rayon::prelude::*;
+        buffers.into_par_iter().enumerate().map(step).collect()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let outcomes: Vec<(usize, Outcome<R, B>)> = buffers.into_iter().enumerate().map(step).collect();
+
+    let mut report = BatchReport {
+        retained: Vec::new(),
+        errors: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for (index, outcome) in outcomes {
+        match outcome {
+            Outcome::Retained(retained) => report.retained.push((index, retained)),
+            Outcome::Failed(err) => report.errors.push((index, err)),
+            Outcome::Skipped(data) => report.skipped.push((index, data)),
+        }
+    }
+    report
+}
@@ -0,0 +1,244 @@
+//! This module focuses on building flatbuffers straight into retained
+//! values while reusing one builder allocation across messages.
+//!
+//! A WIPOffset can be made up with `WIPOffset::new` in safe code, so a
+//! finished builder is not known to hold a valid flatbuffer. The checked
+//! finish functions therefore still run the verifier, and the unchecked
+//! ones are unsafe.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use flatbuffers::{FlatBufferBuilder, InvalidFlatbuffer, VerifierOptions, WIPOffset};
+
+use crate::{FlatbufferRetained, RetainedRoot, SizePrefixedFlatbufferRetained, StableBytes};
+
+/// A FlatBufferBuilder that finishes roots of type T into retained values.
+///
+/// Tables are created through the wrapped builder, which this type
+/// derefs to. Finishing copies the flatbuffer into storage of its own
+/// size and resets the builder, keeping its allocation for the next
+/// message.
+pub struct RetainedBuilder<'fbb, T, B = Vec<u8>> {
+    builder: FlatBufferBuilder<'fbb>,
+    phantom: PhantomData<fn() -> (T, B)>,
+}
+
+impl<'fbb, T, B> RetainedBuilder<'fbb, T, B>
+where
+    T: RetainedRoot,
+    B: StableBytes + From<Vec<u8>>,
+{
+    /// Make a new RetainedBuilder with an empty FlatBufferBuilder.
+    pub fn new() -> Self {
+        Self::from_builder(FlatBufferBuilder::new())
+    }
+
+    /// Make a new RetainedBuilder whose FlatBufferBuilder starts with
+    /// `capacity` bytes allocated.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::from_builder(FlatBufferBuilder::with_capacity(capacity))
+    }
+
+    /// Make a new RetainedBuilder around an existing FlatBufferBuilder.
+    /// The builder is reset first.
+    pub fn from_builder(mut builder: FlatBufferBuilder<'fbb>) -> Self {
+        builder.reset();
+        RetainedBuilder {
+            builder,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Return the wrapped FlatBufferBuilder.
+    pub fn into_inner(self) -> FlatBufferBuilder<'fbb> {
+        self.builder
+    }
+
+    /// Finish an unprefixed flatbuffer with `root` as its root, then
+    /// reset the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The offset of the root table.
+    /// * `file_identifier` - An optional 4 byte file identifier.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn finish(
+        &mut self,
+        root: WIPOffset<T::Table<'fbb>>,
+        file_identifier: Option<&str>,
+    ) -> Result<FlatbufferRetained<T, B>, InvalidFlatbuffer> {
+        self.finish_with_options(root, file_identifier, &VerifierOptions::default())
+    }
+
+    /// Finish an unprefixed flatbuffer with `root` as its root, verifying
+    /// it with custom options, then reset the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The offset of the root table.
+    /// * `file_identifier` - An optional 4 byte file identifier.
+    /// * `opts` - The verifier options to verify the data with.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn finish_with_options(
+        &mut self,
+        root: WIPOffset<T::Table<'fbb>>,
+        file_identifier: Option<&str>,
+        opts: &VerifierOptions,
+    ) -> Result<FlatbufferRetained<T, B>, InvalidFlatbuffer> {
+        self.builder.finish(root, file_identifier);
+        let data = self.take();
+        FlatbufferRetained::new_with_options(data, opts)
+    }
+
+    /// Finish an unprefixed flatbuffer with `root` as its root without
+    /// verifying it, then reset the builder.
+    /// The data is still verified when debug_assertions are enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The offset of the root table.
+    /// * `file_identifier` - An optional 4 byte file identifier.
+    ///
+    /// # Safety
+    ///
+    /// `root` and every offset reachable from it must have been
+    /// returned by the generated create functions on this builder
+    /// since it was last finished.
+    pub unsafe fn finish_unchecked(
+        &mut self,
+        root: WIPOffset<T::Table<'fbb>>,
+        file_identifier: Option<&str>,
+    ) -> FlatbufferRetained<T, B> {
+        self.builder.finish(root, file_identifier);
+        let data = self.take();
+        debug_assert_eq!(
+            FlatbufferRetained::<T, B>::verify(data.as_ref(), &VerifierOptions::default()),
+            Ok(())
+        );
+        FlatbufferRetained::new_unchecked(data)
+    }
+
+    /// Finish a size-prefixed flatbuffer with `root` as its root, then
+    /// reset the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The offset of the root table.
+    /// * `file_identifier` - An optional 4 byte file identifier.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn finish_size_prefixed(
+        &mut self,
+        root: WIPOffset<T::Table<'fbb>>,
+        file_identifier: Option<&str>,
+    ) -> Result<SizePrefixedFlatbufferRetained<T, B>, InvalidFlatbuffer> {
+        self.finish_size_prefixed_with_options(root, file_identifier, &VerifierOptions::default())
+    }
+
+    /// Finish a size-prefixed flatbuffer with `root` as its root,
+    /// verifying it with custom options, then reset the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The offset of the root table.
+    /// * `file_identifier` - An optional 4 byte file identifier.
+    /// * `opts` - The verifier options to verify the data with.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn finish_size_prefixed_with_options(
+        &mut self,
+        root: WIPOffset<T::Table<'fbb>>,
+        file_identifier: Option<&str>,
+        opts: &VerifierOptions,
+    ) -> Result<SizePrefixedFlatbufferRetained<T, B>, InvalidFlatbuffer> {
+        self.builder.finish_size_prefixed(root, file_identifier);
+        let data = self.take();
+        SizePrefixedFlatbufferRetained::new_with_options(data, opts)
+    }
+
+    /// Finish a size-prefixed flatbuffer with `root` as its root without
+    /// verifying it, then reset the builder.
+    /// The data is still verified when debug_assertions are enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The offset of the root table.
+    /// * `file_identifier` - An optional 4 byte file identifier.
+    ///
+    /// # Safety
+    ///
+    /// `root` and every offset reachable from it must have been
+    /// returned by the generated create functions on this builder
+    /// since it was last finished.
+    pub unsafe fn finish_size_prefixed_unchecked(
+        &mut self,
+        root: WIPOffset<T::Table<'fbb>>,
+        file_identifier: Option<&str>,
+    ) -> SizePrefixedFlatbufferRetained<T, B> {
+        self.builder.finish_size_prefixed(root, file_identifier);
+        let data = self.take();
+        debug_assert_eq!(
+            SizePrefixedFlatbufferRetained::<T, B>::verify(
+                data.as_ref(),
+                &VerifierOptions::default()
+            ),
+            Ok(())
+        );
+        SizePrefixedFlatbufferRetained::new_unchecked(data)
+    }
+
+    /// Copy the finished data out of the builder and reset it,
+    /// keeping its allocation.
+    fn take(&mut self) -> B {
+        let data = Vec::from(self.builder.finished_data());
+        self.builder.reset();
+        data.into()
+    }
+}
+
+impl<'fbb, T, B> Default for RetainedBuilder<'fbb, T, B>
+where
+    T: RetainedRoot,
+    B: StableBytes + From<Vec<u8>>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'fbb, T, B> Deref for RetainedBuilder<'fbb, T, B> {
+    type Target = FlatBufferBuilder<'fbb>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.builder
+    }
+}
+
+impl<'fbb, T, B> DerefMut for RetainedBuilder<'fbb, T, B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.builder
+    }
+}
+
+impl<'fbb, T, B> core::fmt::Debug for RetainedBuilder<'fbb, T, B> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RetainedBuilder")
+            .field("builder", &self.builder)
+            .finish()
+    }
+}
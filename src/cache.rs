@@ -0,0 +1,175 @@
+//! This module focuses on remembering which buffers already passed
+//! verification, so byte-identical buffers seen again can skip the
+//! verifier pass.
+
+use flatbuffers::{InvalidFlatbuffer, VerifierOptions};
+use sha2::{Digest, Sha256};
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// Everything a verification outcome depends on. Two buffers only share
+/// an entry when their contents hash identically and they were verified
+/// as the same root type, framing and options.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct CacheKey {
+    digest: [u8; 32],
+    root: TypeId,
+    size_prefixed: bool,
+    max_depth: usize,
+    max_tables: usize,
+    max_apparent_size: usize,
+    ignore_missing_null_terminator: bool,
+}
+
+impl CacheKey {
+    fn new(data: &[u8], root: TypeId, size_prefixed: bool, opts: &VerifierOptions) -> Self {
+        // Destructure so that a new verifier option cannot be left out of the key.
+        let VerifierOptions {
+            max_depth,
+            max_tables,
+            max_apparent_size,
+            ignore_missing_null_terminator,
+        } = *opts;
+        CacheKey {
+            digest: Sha256::digest(data).into(),
+            root,
+            size_prefixed,
+            max_depth,
+            max_tables,
+            max_apparent_size,
+            ignore_missing_null_terminator,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    /// The last use of each entry.
+    entries: HashMap<CacheKey, u64>,
+    /// The entries ordered by last use, oldest first.
+    by_use: BTreeMap<u64, CacheKey>,
+    /// The stamp handed to the next use.
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl CacheState {
+    fn touch(&mut self, key: &CacheKey) -> bool {
+        let stamp = self.clock;
+        match self.entries.get_mut(key) {
+            Some(last_use) => {
+                self.by_use.remove(last_use);
+                *last_use = stamp;
+                self.by_use.insert(stamp, key.clone());
+                self.clock += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, key: CacheKey, capacity: usize) {
+        if capacity == 0 || self.touch(&key) {
+            return;
+        }
+        while self.entries.len() >= capacity {
+            match self.by_use.pop_first() {
+                Some((_, oldest)) => {
+                    self.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        let stamp = self.clock;
+        self.clock += 1;
+        self.by_use.insert(stamp, key.clone());
+        self.entries.insert(key, stamp);
+    }
+}
+
+/// A bounded record of buffers that passed verification, keyed by a
+/// SHA-256 hash of their contents together with the root type, framing
+/// and verifier options they were checked with. Only successful
+/// verifications are remembered, and the least recently used entry is
+/// evicted once the cache is full. The cache may be shared between
+/// threads.
+#[derive(Debug)]
+pub struct VerificationCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+impl VerificationCache {
+    /// Make an empty cache remembering at most `capacity` buffers.
+    pub fn new(capacity: usize) -> Self {
+        VerificationCache {
+            capacity,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Return the most buffers the cache remembers at once.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Return the number of buffers currently remembered.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Return whether no buffers are currently remembered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return how many lookups skipped the verifier.
+    pub fn hits(&self) -> u64 {
+        self.lock().hits
+    }
+
+    /// Return how many lookups had to run the verifier.
+    pub fn misses(&self) -> u64 {
+        self.lock().misses
+    }
+
+    /// Forget every remembered buffer and reset the counters.
+    pub fn clear(&self) {
+        *self.lock() = CacheState::default();
+    }
+
+    /// Run `verify` over `data` unless an identical buffer already
+    /// passed it for the same root, framing and options.
+    pub(crate) fn verify(
+        &self,
+        data: &[u8],
+        root: TypeId,
+        size_prefixed: bool,
+        opts: &VerifierOptions,
+        verify: impl FnOnce(&[u8], &VerifierOptions) -> Result<(), InvalidFlatbuffer>,
+    ) -> Result<(), InvalidFlatbuffer> {
+        let key = CacheKey::new(data, root, size_prefixed, opts);
+        {
+            let mut state = self.lock();
+            if state.touch(&key) {
+                state.hits += 1;
+                return Ok(());
+            }
+            state.misses += 1;
+        }
+        // Verify without holding the lock so other threads are not stalled.
+        verify(data, opts)?;
+        self.lock().insert(key, self.capacity);
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        // The state is consistent between statements, so a panic on
+        // another thread cannot leave it half updated.
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
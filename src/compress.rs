@@ -0,0 +1,73 @@
+//! This module focuses on reading and writing retained flatbuffers as
+//! zstd or lz4 compressed frames, with a cap on the decompressed size
+//! so that a small frame cannot expand without bound before it is
+//! verified.
+
+use std::io::Read;
+
+use crate::Error;
+
+/// Read all of `decoder`, failing once more than `max_size` bytes come out.
+fn read_limited(decoder: impl Read, max_size: usize) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    decoder
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|err| Error::Decompression(err.to_string()))?;
+    if data.len() > max_size {
+        return Err(Error::DecompressedTooLarge { limit: max_size });
+    }
+    Ok(data)
+}
+
+/// Compress `bytes` into a zstd frame at compression `level`. The frame
+/// records its content size, so its window is no larger than `bytes`.
+#[cfg(feature = "zstd")]
+pub(crate) fn zstd(bytes: &[u8], level: i32) -> std::io::Result<Vec<u8>> {
+    zstd::bulk::compress(bytes, level)
+}
+
+/// Return the base 2 logarithm of the largest zstd window a frame
+/// expanding to at most `max_size` bytes needs, within the range zstd
+/// accepts.
+#[cfg(feature = "zstd")]
+fn zstd_window_log(max_size: usize) -> u32 {
+    const WINDOWLOG_MIN: u32 = 10;
+    const WINDOWLOG_MAX: u32 = if cfg!(target_pointer_width = "64") {
+        31
+    } else {
+        30
+    };
+    let log = usize::BITS - max_size.saturating_sub(1).leading_zeros();
+    log.clamp(WINDOWLOG_MIN, WINDOWLOG_MAX)
+}
+
+/// Decompress the zstd frame `frame`, which may expand to at most `max_size` bytes.
+/// Frames asking for a window larger than `max_size` are refused before
+/// the decoder allocates it.
+#[cfg(feature = "zstd")]
+pub(crate) fn unzstd(frame: &[u8], max_size: usize) -> Result<Vec<u8>, Error> {
+    let mut decoder = zstd::stream::read::Decoder::new(frame)
+        .map_err(|err| Error::Decompression(err.to_string()))?;
+    decoder
+        .window_log_max(zstd_window_log(max_size))
+        .map_err(|err| Error::Decompression(err.to_string()))?;
+    read_limited(decoder, max_size)
+}
+
+/// Compress `bytes` into an lz4 frame.
+#[cfg(feature = "lz4")]
+pub(crate) fn lz4(bytes: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+    let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+    encoder
+        .write_all(bytes)
+        .expect("writing to a Vec cannot fail");
+    encoder.finish().expect("writing to a Vec cannot fail")
+}
+
+/// Decompress the lz4 frame `frame`, which may expand to at most `max_size` bytes.
+#[cfg(feature = "lz4")]
+pub(crate) fn unlz4(frame: &[u8], max_size: usize) -> Result<Vec<u8>, Error> {
+    read_limited(lz4_flex::frame::FrameDecoder::new(frame), max_size)
+}
@@ -0,0 +1,103 @@
+//! This module defines a macro that retains buffers carrying one of
+//! several root types, picking the root type from the file identifier.
+
+/// Generate an enum with one retained variant per root type, and
+/// constructors that pick the variant from the file identifier of the
+/// data, verify it as that root type, and fail with
+/// Error::UnknownIdentifier when no variant claims the identifier.
+///
+/// Every root type must implement RetainedRoot, for example with the
+/// retained_root! macro, and every identifier must be 4 bytes long.
+/// The enum is generic over the storage B, which defaults to `Vec<u8>`.
+///
+/// ```ignore
+/// flatbuffers_retained::retained_root!(Monster, Weapon);
+/// flatbuffers_retained::retained_dispatch! {
+///     #[derive(Debug)]
+///     pub enum Message {
+///         Monster(Monster) = MONSTER_IDENTIFIER,
+///         Weapon(my_game::sample::Weapon) = "WEAP",
+///     }
+/// }
+///
+/// match Message::from_bytes(data)? {
+///     Message::Monster(monster) => println!("{}", monster.get().hp()),
+///     Message::Weapon(weapon) => println!("{}", weapon.get().damage()),
+/// }
+/// ```
+#[macro_export]
+macro_rules! retained_dispatch {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($variant:ident($($table:ident)::+) = $identifier:expr),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name<B = $crate::__private::Vec<u8>> {
+            $($variant($crate::Retained<$($table)::+<'static>, B>),)+
+        }
+
+        $(
+            const _: () = assert!(
+                $identifier.len() == $crate::__private::FILE_IDENTIFIER_LENGTH,
+                "file identifiers are 4 bytes long"
+            );
+        )+
+
+        impl<B: $crate::StableBytes> $name<B> {
+            /// Retain an unprefixed flatbuffer as the variant
+            /// matching its file identifier.
+            $vis fn from_bytes(data: B) -> Result<Self, $crate::Error> {
+                Self::from_bytes_with_options(data, &Default::default())
+            }
+
+            /// Retain an unprefixed flatbuffer as the variant matching
+            /// its file identifier, verifying it with custom options.
+            $vis fn from_bytes_with_options(
+                data: B,
+                opts: &$crate::__private::VerifierOptions,
+            ) -> Result<Self, $crate::Error> {
+                let found = $crate::__private::read_identifier(data.as_ref(), false);
+                $(
+                    if found.as_ref().map(|found| &found[..]) == Some($identifier.as_bytes()) {
+                        return Ok($name::$variant(
+                            $crate::Retained::new_unprefixed_with_options(data, opts)?,
+                        ));
+                    }
+                )+
+                Err($crate::Error::UnknownIdentifier { found })
+            }
+
+            /// Retain a size-prefixed flatbuffer as the variant
+            /// matching its file identifier.
+            $vis fn from_size_prefixed_bytes(data: B) -> Result<Self, $crate::Error> {
+                Self::from_size_prefixed_bytes_with_options(data, &Default::default())
+            }
+
+            /// Retain a size-prefixed flatbuffer as the variant matching
+            /// its file identifier, verifying it with custom options.
+            $vis fn from_size_prefixed_bytes_with_options(
+                data: B,
+                opts: &$crate::__private::VerifierOptions,
+            ) -> Result<Self, $crate::Error> {
+                let found = $crate::__private::read_identifier(data.as_ref(), true);
+                $(
+                    if found.as_ref().map(|found| &found[..]) == Some($identifier.as_bytes()) {
+                        return Ok($name::$variant(
+                            $crate::Retained::new_size_prefixed_with_options(data, opts)?,
+                        ));
+                    }
+                )+
+                Err($crate::Error::UnknownIdentifier { found })
+            }
+
+            /// Return the file identifier of the variant.
+            $vis fn identifier(&self) -> &'static str {
+                match self {
+                    $($name::$variant(_) => $identifier,)+
+                }
+            }
+        }
+    };
+}
@@ -0,0 +1,126 @@
+//! This module focuses on an authenticated envelope around verified
+//! flatbuffers, so buffers this process wrote can be reloaded without
+//! another verifier pass.
+//!
+//! An envelope is an 80 byte header followed by the flatbuffer:
+//!
+//! | bytes  | contents                                              |
+//! |--------|-------------------------------------------------------|
+//! | 0..4   | the magic number `FBRE`                               |
+//! | 4      | the format version, currently 1                       |
+//! | 5      | flags; bit 0 is set for a size-prefixed flatbuffer    |
+//! | 6..8   | reserved, zero                                        |
+//! | 8..16  | the root type identifier                              |
+//! | 16..48 | the schema fingerprint                                |
+//! | 48..80 | HMAC-SHA256 of bytes 0..48 followed by the flatbuffer |
+
+use alloc::vec::Vec;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::Error;
+
+/// The length of the header in front of the flatbuffer in an envelope.
+pub const ENVELOPE_HEADER_LEN: usize = 80;
+
+const MAGIC: &[u8; 4] = b"FBRE";
+const VERSION: u8 = 1;
+const FLAG_SIZE_PREFIXED: u8 = 1;
+const MAC_START: usize = 48;
+
+/// The secret and schema an envelope is sealed with. Loading only skips
+/// verification when the envelope was sealed under the same key and
+/// fingerprint.
+#[derive(Clone)]
+pub struct EnvelopeKey {
+    mac_key: Vec<u8>,
+    schema_fingerprint: [u8; 32],
+}
+
+impl EnvelopeKey {
+    /// Make a new EnvelopeKey.
+    ///
+    /// # Arguments
+    ///
+    /// * `mac_key` - The local secret the HMAC is computed under.
+    /// * `schema_fingerprint` - Identifies the schema the flatbuffers
+    ///   follow, such as a hash of the .bfbs file, so that envelopes
+    ///   written under an older schema are verified again.
+    pub fn new(mac_key: &[u8], schema_fingerprint: [u8; 32]) -> Self {
+        EnvelopeKey {
+            mac_key: mac_key.to_vec(),
+            schema_fingerprint,
+        }
+    }
+
+    /// Return the schema fingerprint.
+    pub fn schema_fingerprint(&self) -> &[u8; 32] {
+        &self.schema_fingerprint
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(&self.mac_key).expect("HMAC accepts keys of any length")
+    }
+}
+
+impl core::fmt::Debug for EnvelopeKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EnvelopeKey")
+            .field("schema_fingerprint", &self.schema_fingerprint)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Identify a root type by a hash of its name. The name is only stable
+/// within a build, which at worst costs a full verification on reload.
+fn root_id(root: &str) -> [u8; 8] {
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&Sha256::digest(root.as_bytes())[..8]);
+    id
+}
+
+/// Wrap the verified flatbuffer `bytes` in an envelope.
+pub(crate) fn seal(bytes: &[u8], root: &str, size_prefixed: bool, key: &EnvelopeKey) -> Vec<u8> {
+    let mut envelope = Vec::with_capacity(ENVELOPE_HEADER_LEN + bytes.len());
+    envelope.extend_from_slice(MAGIC);
+    envelope.push(VERSION);
+    envelope.push(if size_prefixed { FLAG_SIZE_PREFIXED } else { 0 });
+    envelope.extend_from_slice(&[0, 0]);
+    envelope.extend_from_slice(&root_id(root));
+    envelope.extend_from_slice(&key.schema_fingerprint);
+    let mut mac = key.mac();
+    mac.update(&envelope);
+    mac.update(bytes);
+    envelope.extend_from_slice(&mac.finalize().into_bytes());
+    envelope.extend_from_slice(bytes);
+    envelope
+}
+
+/// Check the header of the envelope `data` and return whether its
+/// flatbuffer was sealed under `key` for the same root type and
+/// framing, in which case it does not need verifying again.
+///
+/// # Errors
+///
+/// Returns Error::MalformedEnvelope if `data` does not start with an
+/// envelope header this version understands.
+pub(crate) fn open(
+    data: &[u8],
+    root: &str,
+    size_prefixed: bool,
+    key: &EnvelopeKey,
+) -> Result<bool, Error> {
+    if data.len() < ENVELOPE_HEADER_LEN || &data[..4] != MAGIC || data[4] != VERSION {
+        return Err(Error::MalformedEnvelope);
+    }
+    let flags = if size_prefixed { FLAG_SIZE_PREFIXED } else { 0 };
+    if data[5] != flags || data[8..16] != root_id(root) || data[16..48] != key.schema_fingerprint {
+        return Ok(false);
+    }
+    let mut mac = key.mac();
+    mac.update(&data[..MAC_START]);
+    mac.update(&data[ENVELOPE_HEADER_LEN..]);
+    Ok(mac
+        .verify_slice(&data[MAC_START..ENVELOPE_HEADER_LEN])
+        .is_ok())
+}
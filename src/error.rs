@@ -0,0 +1,277 @@
+//! This module defines the errors that can be returned when a buffer
+//! is rejected for reasons beyond structural flatbuffer verification.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use flatbuffers::{InvalidFlatbuffer, FILE_IDENTIFIER_LENGTH, SIZE_SIZEPREFIX, SIZE_UOFFSET};
+
+/// An error returned when retaining a flatbuffer fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The data is not a valid flatbuffer of the requested type.
+    InvalidFlatbuffer(InvalidFlatbuffer),
+    /// The file identifier stored in the data does not match the
+    /// expected one. `found` is None if the data is too short
+    /// to hold an identifier at all.
+    IdentifierMismatch {
+        expected: [u8; FILE_IDENTIFIER_LENGTH],
+        found: Option<[u8; FILE_IDENTIFIER_LENGTH]>,
+    },
+    /// No root type is registered for the file identifier stored in
+    /// the data. `found` is None if the data is too short to hold an
+    /// identifier at all.
+    UnknownIdentifier {
+        found: Option<[u8; FILE_IDENTIFIER_LENGTH]>,
+    },
+    /// The size prefix does not declare the number of bytes after it.
+    /// `declared` is 0 if the data is too short to hold a size prefix.
+    SizePrefixMismatch { declared: usize, actual: usize },
+    /// Bytes other than builder padding follow the `extent` bytes the
+    /// verifier read, out of `len` bytes in total.
+    TrailingBytes { extent: usize, len: usize },
+    /// The flatbuffer bytes no longer match the checksum recorded when
+    /// they were retained, so they changed after verification.
+    #[cfg(feature = "checksum")]
+    ChecksumMismatch { expected: u32, found: u32 },
+    /// The data does not start with an envelope header this version
+    /// of the library understands.
+    #[cfg(feature = "envelope")]
+    MalformedEnvelope,
+    /// The compressed frame could not be decompressed.
+    #[cfg(any(feature = "zstd", feature = "lz4"))]
+    Decompression(String),
+    /// The compressed frame expands to more than `limit` bytes.
+    #[cfg(any(feature = "zstd", feature = "lz4"))]
+    DecompressedTooLarge { limit: usize },
+}
+
+impl From<InvalidFlatbuffer> for Error {
+    fn from(err: InvalidFlatbuffer) -> Self {
+        Error::InvalidFlatbuffer(err)
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::InvalidFlatbuffer(err) => err.fmt(f),
+            Error::IdentifierMismatch {
+                expected,
+                found: Some(found),
+            } => write!(
+                f,
+                "File identifier mismatch: expected {:?}, found {:?}",
+                String::from_utf8_lossy(expected),
+                String::from_utf8_lossy(found)
+            ),
+            Error::IdentifierMismatch {
+                expected,
+                found: None,
+            } => write!(
+                f,
+                "File identifier mismatch: expected {:?}, but the buffer is too short",
+                String::from_utf8_lossy(expected)
+            ),
+            Error::UnknownIdentifier { found: Some(found) } => write!(
+                f,
+                "Unknown file identifier {:?}",
+                String::from_utf8_lossy(found)
+            ),
+            Error::UnknownIdentifier { found: None } => write!(
+                f,
+                "Unknown file identifier: the buffer is too short to hold one"
+            ),
+            Error::SizePrefixMismatch { declared, actual } => write!(
+                f,
+                "Size prefix mismatch: declared {} bytes, found {}",
+                declared, actual
+            ),
+            Error::TrailingBytes { extent, len } => write!(
+                f,
+                "Trailing bytes: the flatbuffer ends at {} of {} bytes",
+                extent, len
+            ),
+            #[cfg(feature = "checksum")]
+            Error::ChecksumMismatch { expected, found } => write!(
+                f,
+                "Checksum mismatch: expected {:#010x}, found {:#010x}",
+                expected, found
+            ),
+            #[cfg(feature = "envelope")]
+            Error::MalformedEnvelope => write!(f, "Malformed envelope header"),
+            #[cfg(any(feature = "zstd", feature = "lz4"))]
+            Error::Decompression(reason) => write!(f, "Decompression failed: {}", reason),
+            #[cfg(any(feature = "zstd", feature = "lz4"))]
+            Error::DecompressedTooLarge { limit } => {
+                write!(f, "Decompressed data exceeds the limit of {} bytes", limit)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidFlatbuffer(err) => Some(err),
+            Error::IdentifierMismatch { .. } | Error::UnknownIdentifier { .. } => None,
+            Error::SizePrefixMismatch { .. } | Error::TrailingBytes { .. } => None,
+            #[cfg(feature = "checksum")]
+            Error::ChecksumMismatch { .. } => None,
+            #[cfg(feature = "envelope")]
+            Error::MalformedEnvelope => None,
+            #[cfg(any(feature = "zstd", feature = "lz4"))]
+            Error::Decompression(_) | Error::DecompressedTooLarge { .. } => None,
+        }
+    }
+}
+
+/// An error returned when a buffer of unknown framing verifies neither
+/// as an unprefixed nor as a size-prefixed flatbuffer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AutoDetectError {
+    /// Why the data is not a valid unprefixed flatbuffer.
+    pub unprefixed: Box<InvalidFlatbuffer>,
+    /// Why the data is not a valid size-prefixed flatbuffer.
+    pub size_prefixed: Box<InvalidFlatbuffer>,
+}
+
+impl core::fmt::Display for AutoDetectError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Invalid as unprefixed flatbuffer ({}) and as size-prefixed flatbuffer ({})",
+            self.unprefixed, self.size_prefixed
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AutoDetectError {}
+
+/// An error returned when verification rejects a buffer, which hands
+/// the rejected storage back so it can be logged, retried with a
+/// different framing or reused.
+pub struct RetainError<B = Vec<u8>, E = InvalidFlatbuffer> {
+    /// Why the data was rejected.
+    error: E,
+    /// The storage that was rejected.
+    data: B,
+}
+
+impl<B, E> RetainError<B, E> {
+    /// Make a new RetainError from the rejection reason and the storage.
+    pub fn new(error: E, data: B) -> Self {
+        RetainError { error, data }
+    }
+
+    /// Return why the data was rejected.
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Return a reference to the rejected storage.
+    pub fn data(&self) -> &B {
+        &self.data
+    }
+
+    /// Deconstruct this error and return the rejected storage.
+    pub fn into_inner(self) -> B {
+        self.data
+    }
+
+    /// Deconstruct this error into the rejection reason and the storage.
+    pub fn into_parts(self) -> (E, B) {
+        (self.error, self.data)
+    }
+}
+
+impl<B: AsRef<[u8]>, E: core::fmt::Debug> core::fmt::Debug for RetainError<B, E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RetainError")
+            .field("error", &self.error)
+            .field("len", &self.data.as_ref().len())
+            .finish()
+    }
+}
+
+impl<B, E: core::fmt::Display> core::fmt::Display for RetainError<B, E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<B, E> std::error::Error for RetainError<B, E>
+where
+    B: AsRef<[u8]>,
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<B> From<RetainError<B, InvalidFlatbuffer>> for InvalidFlatbuffer {
+    fn from(err: RetainError<B, InvalidFlatbuffer>) -> Self {
+        err.error
+    }
+}
+
+/// Return the 4 byte file identifier stored in `data`, or None if
+/// `data` is too short to hold one. The identifier follows the root
+/// offset, which itself follows the size prefix when `size_prefixed`
+/// is set.
+pub fn read_identifier(data: &[u8], size_prefixed: bool) -> Option<[u8; FILE_IDENTIFIER_LENGTH]> {
+    let start = if size_prefixed {
+        SIZE_SIZEPREFIX + SIZE_UOFFSET
+    } else {
+        SIZE_UOFFSET
+    };
+    data.get(start..start + FILE_IDENTIFIER_LENGTH)
+        .map(|found| {
+            let mut ident = [0u8; FILE_IDENTIFIER_LENGTH];
+            ident.copy_from_slice(found);
+            ident
+        })
+}
+
+/// Check that `data` carries the 4 byte file identifier `identifier`.
+///
+/// # Panics
+///
+/// Panics if `identifier` is not exactly 4 bytes long, like
+/// flatbuffers::buffer_has_identifier.
+pub(crate) fn check_identifier(
+    data: &[u8],
+    identifier: &str,
+    size_prefixed: bool,
+) -> Result<(), Error> {
+    assert_eq!(identifier.len(), FILE_IDENTIFIER_LENGTH);
+    let mut expected = [0u8; FILE_IDENTIFIER_LENGTH];
+    expected.copy_from_slice(identifier.as_bytes());
+    let found = read_identifier(data, size_prefixed);
+    if found == Some(expected) {
+        Ok(())
+    } else {
+        Err(Error::IdentifierMismatch { expected, found })
+    }
+}
+
+/// Check that `data` still hashes to the checksum `expected`, if one
+/// was recorded.
+#[cfg(feature = "checksum")]
+pub(crate) fn check_checksum(data: &[u8], expected: Option<u32>) -> Result<(), Error> {
+    match expected {
+        Some(expected) => {
+            let found = crc32fast::hash(data);
+            if found == expected {
+                Ok(())
+            } else {
+                Err(Error::ChecksumMismatch { expected, found })
+            }
+        }
+        None => Ok(()),
+    }
+}
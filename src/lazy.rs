@@ -0,0 +1,186 @@
+//! This module focuses on flatbuffers that are stored unverified and
+//! only verified the first time their root is accessed, so buffers
+//! that are never read never pay for verification.
+
+use flatbuffers::{
+    root_unchecked, size_prefixed_root_unchecked, InvalidFlatbuffer, VerifierOptions,
+};
+use std::marker::PhantomData;
+use std::sync::OnceLock;
+
+use crate::{
+    FlatbufferRetained, RetainError, Retained, RetainedRoot, SizePrefixedFlatbufferRetained,
+    StableBytes,
+};
+
+/// This struct holds data that is expected to be a flatbuffer of type T,
+/// either unprefixed or size-prefixed. The data is verified the first
+/// time the root is accessed and the outcome is cached, so the
+/// verifier runs at most once.
+#[derive(Debug)]
+pub struct LazyRetained<T, B = Vec<u8>> {
+    /// Data that has not necessarily been verified yet.
+    data: B,
+    /// Whether the data is prefixed by its size.
+    size_prefixed: bool,
+    /// The limits the verifier enforces when it runs.
+    opts: VerifierOptions,
+    /// The outcome of verification, once it has run.
+    verified: OnceLock<Result<(), InvalidFlatbuffer>>,
+    /// Phantom data to place-hold which T data will be validated for.
+    phantom: PhantomData<T>,
+}
+
+impl<T, B> LazyRetained<T, B>
+where
+    T: RetainedRoot,
+    B: StableBytes,
+{
+    /// Make a new LazyRetained class of type T from an unprefixed
+    /// byte buffer without verifying it yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that should represent a flatbuffer of type T,
+    ///   not prefixed by size.
+    pub fn new_unprefixed(data: B) -> Self {
+        Self::new_unprefixed_with_options(data, VerifierOptions::default())
+    }
+
+    /// Make a new LazyRetained class of type T from a size-prefixed
+    /// byte buffer without verifying it yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that should represent a flatbuffer of type T,
+    ///   prefixed by size.
+    pub fn new_size_prefixed(data: B) -> Self {
+        Self::new_size_prefixed_with_options(data, VerifierOptions::default())
+    }
+
+    /// Make a new LazyRetained class of type T from an unprefixed
+    /// byte buffer that will be verified with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that should represent a flatbuffer of type T,
+    ///   not prefixed by size.
+    /// * `opts` - The limits the verifier enforces when it runs.
+    pub fn new_unprefixed_with_options(data: B, opts: VerifierOptions) -> Self {
+        Self::from_parts(data, false, opts)
+    }
+
+    /// Make a new LazyRetained class of type T from a size-prefixed
+    /// byte buffer that will be verified with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that should represent a flatbuffer of type T,
+    ///   prefixed by size.
+    /// * `opts` - The limits the verifier enforces when it runs.
+    pub fn new_size_prefixed_with_options(data: B, opts: VerifierOptions) -> Self {
+        Self::from_parts(data, true, opts)
+    }
+
+    /// Wrap data that will be verified in the given framing.
+    fn from_parts(data: B, size_prefixed: bool, opts: VerifierOptions) -> Self {
+        LazyRetained {
+            data,
+            size_prefixed,
+            opts,
+            verified: OnceLock::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Verify the data unless that has already happened and
+    /// return the cached outcome.
+    fn verify(&self) -> &Result<(), InvalidFlatbuffer> {
+        self.verified.get_or_init(|| {
+            if self.size_prefixed {
+                SizePrefixedFlatbufferRetained::<T, B>::verify(self.data.as_ref(), &self.opts)
+            } else {
+                FlatbufferRetained::<T, B>::verify(self.data.as_ref(), &self.opts)
+            }
+        })
+    }
+
+    /// Return a valid root of type T from the flatbuffer buffer stored
+    /// in self, verifying the buffer first if this is the first access.
+    ///
+    /// # Errors
+    ///
+    /// Returns the InvalidFlatbuffer error from run_verifier if the
+    /// data is not a valid flatbuffer of type T. The error is cached
+    /// and returned again on later calls.
+    pub fn try_get(&self) -> Result<T::Table<'_>, InvalidFlatbuffer> {
+        self.verify().clone()?;
+        if self.size_prefixed {
+            Ok(unsafe { size_prefixed_root_unchecked::<T::Table<'_>>(self.data.as_ref()) })
+        } else {
+            Ok(unsafe { root_unchecked::<T::Table<'_>>(self.data.as_ref()) })
+        }
+    }
+
+    /// Convert self into a Retained class, verifying the data first
+    /// unless that has already happened.
+    ///
+    /// # Errors
+    ///
+    /// Returns a RetainError holding the InvalidFlatbuffer error from
+    /// run_verifier together with the rejected data.
+    pub fn into_retained(self) -> Result<Retained<T, B>, RetainError<B>> {
+        if let Err(err) = self.verify() {
+            return Err(RetainError::new(err.clone(), self.data));
+        }
+        // Safety: the data was verified in the framing recorded for it.
+        Ok(if self.size_prefixed {
+            Retained::SizePrefixed(unsafe {
+                SizePrefixedFlatbufferRetained::new_unchecked(self.data)
+            })
+        } else {
+            Retained::Unprefixed(unsafe { FlatbufferRetained::new_unchecked(self.data) })
+        })
+    }
+}
+
+impl<T, B> LazyRetained<T, B> {
+    /// Return whether the data has been verified successfully.
+    /// This does not run the verifier.
+    pub fn is_verified(&self) -> bool {
+        matches!(self.verified.get(), Some(Ok(())))
+    }
+
+    /// Return whether the data is prefixed by its size.
+    pub fn is_size_prefixed(&self) -> bool {
+        self.size_prefixed
+    }
+
+    /// Deconstruct this class and return the storage that
+    /// made up the data within it.
+    pub fn take(self) -> B {
+        self.data
+    }
+
+    /// Return a reference to the storage that makes up the data within,
+    /// which may not have been verified yet.
+    pub fn storage(&self) -> &B {
+        &self.data
+    }
+}
+
+/// Clones carry over the outcome of verification. This relies on
+/// StableBytes, which requires a clone of the storage to hold the same
+/// bytes as the original; with only AsRef and Clone the cached outcome
+/// could vouch for bytes that were never verified.
+impl<T, B: StableBytes + Clone> Clone for LazyRetained<T, B> {
+    fn clone(&self) -> Self {
+        LazyRetained {
+            data: self.data.clone(),
+            size_prefixed: self.size_prefixed,
+            opts: self.opts.clone(),
+            verified: self.verified.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
@@ -1,21 +1,79 @@
 //! This library allows you to validate Flatbuffer buffers once
 //! at initialization time then safely use them, unchecked, later.
+//!
+//! The crate is `no_std` with `alloc` when the default `std` feature
+//! is disabled. LazyRetained and the `cache`, `lz4`, `mmap`, `rayon`
+//! and `zstd` features need `std`.
 
-use flatbuffers::{Follow, InvalidFlatbuffer, Verifiable};
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use flatbuffers::{InvalidFlatbuffer, VerifierOptions, SIZE_SIZEPREFIX};
+
+mod batch;
+mod builder;
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(any(feature = "zstd", feature = "lz4"))]
+mod compress;
+mod dispatch;
+#[cfg(feature = "envelope")]
+mod envelope;
+mod error;
 mod flatbuffer_retained;
+#[cfg(feature = "std")]
+mod lazy;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod options;
+mod retained_ref;
+mod retained_type;
+mod root;
+mod shared;
 mod size_prefixed;
+mod storage;
+mod strict;
+mod trim;
+mod validated;
+pub use batch::{BatchMode, BatchReport};
+pub use builder::RetainedBuilder;
+#[cfg(feature = "cache")]
+pub use cache::VerificationCache;
+#[cfg(feature = "envelope")]
+pub use envelope::{EnvelopeKey, ENVELOPE_HEADER_LEN};
+pub use error::{AutoDetectError, Error, RetainError};
 pub use flatbuffer_retained::FlatbufferRetained;
+#[cfg(feature = "std")]
+pub use lazy::LazyRetained;
+pub use retained_ref::RetainedRef;
+pub use root::RetainedRoot;
+pub use shared::SharedRetained;
+pub use storage::{SharedBytes, StableBytes};
+pub use trim::TrimReport;
+pub use validated::{Validated, ValidationError, Validator};
+
+/// Items used by the macros of this crate.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::error::read_identifier;
+    pub use alloc::vec::Vec;
+    pub use flatbuffers::{InvalidFlatbuffer, Table, VerifierOptions, FILE_IDENTIFIER_LENGTH};
+}
 pub use size_prefixed::SizePrefixedFlatbufferRetained;
 
 #[derive(Debug, Clone)]
-pub enum Retained<'a, T: Follow<'a>> {
-    Unprefixed(FlatbufferRetained<'a, T>),
-    SizePrefixed(SizePrefixedFlatbufferRetained<'a, T>),
+pub enum Retained<T, B = Vec<u8>> {
+    Unprefixed(FlatbufferRetained<T, B>),
+    SizePrefixed(SizePrefixedFlatbufferRetained<T, B>),
 }
-impl<'a, T> Retained<'a, T>
+impl<T, B> Retained<T, B>
 where
-    T: Follow<'a> + Verifiable,
+    T: RetainedRoot,
+    B: StableBytes,
 {
     /// Make a new FlatbufferRetained class of type T from
     /// an unprefixed byte buffer.
@@ -23,14 +81,14 @@ where
     /// # Arguments
     ///
     /// * `data` - The data that represents a flatbuffer of type T.
-    ///            not prefixed by size.
+    ///   not prefixed by size.
     ///
     /// # Errors
     ///
     /// May return any InvalidFlatbuffer error from run_verifier
     /// when it parses the data.
-    pub fn new_unprefixed(data: Vec<u8>) -> Result<Self, InvalidFlatbuffer> {
-        Ok(Retained::Unprefixed(FlatbufferRetained::<T>::new(data)?))
+    pub fn new_unprefixed(data: B) -> Result<Self, InvalidFlatbuffer> {
+        Ok(Retained::Unprefixed(FlatbufferRetained::<T, B>::new(data)?))
     }
     /// Make a new FlatbufferRetained class of type T from
     /// a size-prefixed byte buffer.
@@ -38,104 +96,623 @@ where
     /// # Arguments
     ///
     /// * `data` - The data that represents a flatbuffer of type T,
-    ///            prefixed by size.
+    ///   prefixed by size.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn new_size_prefixed(data: B) -> Result<Self, InvalidFlatbuffer> {
+        Ok(Retained::SizePrefixed(SizePrefixedFlatbufferRetained::<
+            T,
+            B,
+        >::new(data)?))
+    }
+
+    /// Make a new Retained class of type T from an unprefixed
+    /// byte buffer, handing the data back if it is rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T.
+    ///   not prefixed by size.
+    ///
+    /// # Errors
+    ///
+    /// Returns a RetainError holding the InvalidFlatbuffer error from
+    /// run_verifier together with the rejected data.
+    pub fn try_new_unprefixed(data: B) -> Result<Self, RetainError<B>> {
+        Ok(Retained::Unprefixed(FlatbufferRetained::<T, B>::try_new(
+            data,
+        )?))
+    }
+
+    /// Make a new Retained class of type T from a size-prefixed
+    /// byte buffer, handing the data back if it is rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    ///
+    /// # Errors
+    ///
+    /// Returns a RetainError holding the InvalidFlatbuffer error from
+    /// run_verifier together with the rejected data.
+    pub fn try_new_size_prefixed(data: B) -> Result<Self, RetainError<B>> {
+        Ok(Retained::SizePrefixed(SizePrefixedFlatbufferRetained::<
+            T,
+            B,
+        >::try_new(data)?))
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// an unprefixed byte buffer, verifying it with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T.
+    ///   not prefixed by size.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
     ///
     /// # Errors
     ///
     /// May return any InvalidFlatbuffer error from run_verifier
     /// when it parses the data.
-    pub fn new_size_prefixed(data: Vec<u8>) -> Result<Self, InvalidFlatbuffer> {
-        Ok(Retained::SizePrefixed(
-            SizePrefixedFlatbufferRetained::<T>::new(data)?,
+    pub fn new_unprefixed_with_options(
+        data: B,
+        opts: &VerifierOptions,
+    ) -> Result<Self, InvalidFlatbuffer> {
+        Ok(Retained::Unprefixed(
+            FlatbufferRetained::<T, B>::new_with_options(data, opts)?,
+        ))
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed byte buffer, verifying it with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn new_size_prefixed_with_options(
+        data: B,
+        opts: &VerifierOptions,
+    ) -> Result<Self, InvalidFlatbuffer> {
+        Ok(Retained::SizePrefixed(SizePrefixedFlatbufferRetained::<
+            T,
+            B,
+        >::new_with_options(
+            data, opts
+        )?))
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// an unprefixed byte buffer that must carry the given file identifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T.
+    ///   not prefixed by size.
+    /// * `identifier` - The 4 byte file identifier the data must carry.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::IdentifierMismatch if the identifier differs,
+    /// otherwise any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn new_unprefixed_with_identifier(data: B, identifier: &str) -> Result<Self, Error> {
+        Ok(Retained::Unprefixed(
+            FlatbufferRetained::<T, B>::new_with_identifier(data, identifier)?,
+        ))
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// an unprefixed byte buffer that must carry the given file identifier,
+    /// verifying it with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T.
+    ///   not prefixed by size.
+    /// * `identifier` - The 4 byte file identifier the data must carry.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::IdentifierMismatch if the identifier differs,
+    /// otherwise any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn new_unprefixed_with_identifier_and_options(
+        data: B,
+        identifier: &str,
+        opts: &VerifierOptions,
+    ) -> Result<Self, Error> {
+        Ok(Retained::Unprefixed(
+            FlatbufferRetained::<T, B>::new_with_identifier_and_options(data, identifier, opts)?,
+        ))
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed byte buffer that must carry the given file identifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    /// * `identifier` - The 4 byte file identifier the data must carry.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::IdentifierMismatch if the identifier differs,
+    /// otherwise any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn new_size_prefixed_with_identifier(data: B, identifier: &str) -> Result<Self, Error> {
+        Ok(Retained::SizePrefixed(SizePrefixedFlatbufferRetained::<
+            T,
+            B,
+        >::new_with_identifier(
+            data, identifier
+        )?))
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed byte buffer that must carry the given file identifier,
+    /// verifying it with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    /// * `identifier` - The 4 byte file identifier the data must carry.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::IdentifierMismatch if the identifier differs,
+    /// otherwise any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn new_size_prefixed_with_identifier_and_options(
+        data: B,
+        identifier: &str,
+        opts: &VerifierOptions,
+    ) -> Result<Self, Error> {
+        Ok(Retained::SizePrefixed(SizePrefixedFlatbufferRetained::<
+            T,
+            B,
+        >::new_with_identifier_and_options(
+            data, identifier, opts
+        )?))
+    }
+
+    /// Make a new Retained class of type T from
+    /// an unprefixed flatbuffer embedded at `offset` inside `data`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data containing a flatbuffer of type T.
+    /// * `offset` - The offset within data at which the flatbuffer starts.
+    /// * `len` - The length of the flatbuffer, or None if it extends
+    ///   to the end of data.
+    ///
+    /// # Errors
+    ///
+    /// Returns InvalidFlatbuffer::RangeOutOfBounds if the region does
+    /// not fit within data, otherwise any InvalidFlatbuffer error from
+    /// run_verifier when it parses the region.
+    pub fn new_unprefixed_at(
+        data: B,
+        offset: usize,
+        len: Option<usize>,
+    ) -> Result<Self, InvalidFlatbuffer> {
+        Ok(Retained::Unprefixed(FlatbufferRetained::<T, B>::new_at(
+            data, offset, len,
+        )?))
+    }
+
+    /// Make a new Retained class of type T from
+    /// a size-prefixed flatbuffer embedded at `offset` inside `data`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data containing a flatbuffer of type T.
+    /// * `offset` - The offset within data at which the size prefix starts.
+    /// * `len` - The length of the flatbuffer including its size prefix,
+    ///   or None if it extends to the end of data.
+    ///
+    /// # Errors
+    ///
+    /// Returns InvalidFlatbuffer::RangeOutOfBounds if the region does
+    /// not fit within data, otherwise any InvalidFlatbuffer error from
+    /// run_verifier when it parses the region.
+    pub fn new_size_prefixed_at(
+        data: B,
+        offset: usize,
+        len: Option<usize>,
+    ) -> Result<Self, InvalidFlatbuffer> {
+        Ok(Retained::SizePrefixed(SizePrefixedFlatbufferRetained::<
+            T,
+            B,
+        >::new_at(data, offset, len)?))
+    }
+
+    /// Make a new Retained class of type T from a byte buffer
+    /// that may or may not be prefixed by size.
+    ///
+    /// The leading u32 is read as a size prefix. If it equals the
+    /// length of the rest of the buffer, the data is verified as a
+    /// size-prefixed flatbuffer first and as an unprefixed one second.
+    /// Otherwise the unprefixed interpretation is tried first.
+    /// When both interpretations verify, the one tried first wins.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   with or without a size prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns both verification errors if the data is valid in neither
+    /// framing.
+    pub fn new_auto(data: B) -> Result<Self, AutoDetectError> {
+        Self::new_auto_with_options(data, &VerifierOptions::default())
+    }
+
+    /// Make a new Retained class of type T from a byte buffer that
+    /// may or may not be prefixed by size, handing the data back if
+    /// it is rejected. See new_auto for how the framing is chosen.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   with or without a size prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns a RetainError holding both verification errors together
+    /// with the rejected data.
+    pub fn try_new_auto(data: B) -> Result<Self, RetainError<B, AutoDetectError>> {
+        Self::try_new_auto_with_options(data, &VerifierOptions::default())
+    }
+
+    /// Make a new Retained class of type T from a byte buffer
+    /// that may or may not be prefixed by size, verifying it with
+    /// custom options. See new_auto for how the framing is chosen.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   with or without a size prefix.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns both verification errors if the data is valid in neither
+    /// framing.
+    pub fn new_auto_with_options(data: B, opts: &VerifierOptions) -> Result<Self, AutoDetectError> {
+        Self::try_new_auto_with_options(data, opts).map_err(|err| err.into_parts().0)
+    }
+
+    /// Make a new Retained class of type T from a byte buffer that
+    /// may or may not be prefixed by size, verifying it with custom
+    /// options and handing the data back if it is rejected.
+    /// See new_auto for how the framing is chosen.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   with or without a size prefix.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns a RetainError holding both verification errors together
+    /// with the rejected data.
+    pub fn try_new_auto_with_options(
+        data: B,
+        opts: &VerifierOptions,
+    ) -> Result<Self, RetainError<B, AutoDetectError>> {
+        let bytes = data.as_ref();
+        let size_prefix_matches = bytes
+            .get(..SIZE_SIZEPREFIX)
+            .map(|prefix| u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]))
+            .is_some_and(|size| size as usize == bytes.len() - SIZE_SIZEPREFIX);
+        let (unprefixed, size_prefixed) = if size_prefix_matches {
+            let size_prefixed =
+                match SizePrefixedFlatbufferRetained::<T, B>::verify(data.as_ref(), opts) {
+                    // Safety: the data was just verified as size-prefixed.
+                    Ok(()) => {
+                        return Ok(Retained::SizePrefixed(unsafe {
+                            SizePrefixedFlatbufferRetained::new_unchecked(data)
+                        }))
+                    }
+                    Err(err) => err,
+                };
+            match FlatbufferRetained::<T, B>::verify(data.as_ref(), opts) {
+                // Safety: the data was just verified as unprefixed.
+                Ok(()) => {
+                    return Ok(Retained::Unprefixed(unsafe {
+                        FlatbufferRetained::new_unchecked(data)
+                    }))
+                }
+                Err(err) => (err, size_prefixed),
+            }
+        } else {
+            let unprefixed = match FlatbufferRetained::<T, B>::verify(data.as_ref(), opts) {
+                // Safety: the data was just verified as unprefixed.
+                Ok(()) => {
+                    return Ok(Retained::Unprefixed(unsafe {
+                        FlatbufferRetained::new_unchecked(data)
+                    }))
+                }
+                Err(err) => err,
+            };
+            match SizePrefixedFlatbufferRetained::<T, B>::verify(data.as_ref(), opts) {
+                // Safety: the data was just verified as size-prefixed.
+                Ok(()) => {
+                    return Ok(Retained::SizePrefixed(unsafe {
+                        SizePrefixedFlatbufferRetained::new_unchecked(data)
+                    }))
+                }
+                Err(err) => (unprefixed, err),
+            }
+        };
+        Err(RetainError::new(
+            AutoDetectError {
+                unprefixed: Box::new(unprefixed),
+                size_prefixed: Box::new(size_prefixed),
+            },
+            data,
         ))
     }
 
     /// Return a valid root of type T from the flatbuffer
     /// buffer stored in self.
-    pub fn get(&'a self) -> <T as Follow<'a>>::Inner {
+    pub fn get(&self) -> T::Table<'_> {
         match self {
             Retained::Unprefixed(ref a) => a.get(),
             Retained::SizePrefixed(ref a) => a.get(),
         }
     }
+
+    /// Report how much memory trim would save, without copying.
+    pub fn trim_report(&self) -> TrimReport {
+        match self {
+            Retained::Unprefixed(a) => a.trim_report(),
+            Retained::SizePrefixed(a) => a.trim_report(),
+        }
+    }
+
+    /// Copy the flatbuffer into a new Vec holding nothing else,
+    /// dropping any header, trailer and builder slack around it.
+    pub fn trim(&self) -> Retained<T, Vec<u8>> {
+        match self {
+            Retained::Unprefixed(a) => Retained::Unprefixed(a.trim()),
+            Retained::SizePrefixed(a) => Retained::SizePrefixed(a.trim()),
+        }
+    }
+
+    /// Project from the root to a table nested within it, returning an
+    /// owned handle that shares the storage of self. Move `Vec<u8>`
+    /// storage into an `Arc<[u8]>` with `into_arc` first.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Returns the nested table read from the root of self,
+    ///   or None if it is absent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` returns a table that does not belong to this
+    /// buffer or that does not verify as a U.
+    pub fn project<U, F>(&self, f: F) -> Option<RetainedRef<U, B>>
+    where
+        U: RetainedRoot,
+        B: SharedBytes,
+        F: for<'a> FnOnce(&'a Self) -> Option<U::Table<'a>>,
+    {
+        let bytes: &[u8] = self.as_ref();
+        let loc = retained_ref::locate::<U>(bytes, f(self)?);
+        let head = self.head();
+        Some(RetainedRef::new(
+            self.storage().clone(),
+            head,
+            head + bytes.len(),
+            loc,
+        ))
+    }
 }
-impl<'a, T: Follow<'a>> Retained<'a, T> {
-    /// Return an iterator to traverse over the contained Vec.
-    pub fn iter(&self) -> std::slice::Iter<u8> {
+impl<T, B: StableBytes> Retained<T, B> {
+    /// Return an iterator to traverse over the contained data.
+    pub fn iter(&self) -> core::slice::Iter<'_, u8> {
         match self {
             Retained::Unprefixed(a) => a.iter(),
             Retained::SizePrefixed(a) => a.iter(),
         }
     }
 
-    /// Deconstruct this class and return the Vec that
-    /// made up the data within it.
-    pub fn take(self) -> Vec<u8> {
+    /// Return the offset within the storage at which the flatbuffer
+    /// starts.
+    pub fn head(&self) -> usize {
+        match self {
+            Retained::Unprefixed(a) => a.head(),
+            Retained::SizePrefixed(a) => a.head(),
+        }
+    }
+
+    /// Return the bytes in the storage before the flatbuffer,
+    /// such as a custom header.
+    pub fn header(&self) -> &[u8] {
+        match self {
+            Retained::Unprefixed(a) => a.header(),
+            Retained::SizePrefixed(a) => a.header(),
+        }
+    }
+
+    /// Return the bytes in the storage after the flatbuffer.
+    pub fn trailer(&self) -> &[u8] {
+        match self {
+            Retained::Unprefixed(a) => a.trailer(),
+            Retained::SizePrefixed(a) => a.trailer(),
+        }
+    }
+
+    /// Deconstruct this class and return the storage that
+    /// made up the data within it. The flatbuffer starts at
+    /// `head()` within the returned storage and is followed by
+    /// `trailer()`.
+    pub fn take(self) -> B {
         match self {
             Retained::Unprefixed(a) => a.take(),
             Retained::SizePrefixed(a) => a.take(),
         }
     }
 
+    /// Return a reference to the storage that makes up the data within.
+    pub fn storage(&self) -> &B {
+        match self {
+            Retained::Unprefixed(a) => a.storage(),
+            Retained::SizePrefixed(a) => a.storage(),
+        }
+    }
+
+    /// Record a checksum of the flatbuffer bytes so that changes made
+    /// to them after verification can be detected by verify_integrity.
+    #[cfg(feature = "checksum")]
+    pub fn with_checksum(self) -> Self {
+        match self {
+            Retained::Unprefixed(a) => Retained::Unprefixed(a.with_checksum()),
+            Retained::SizePrefixed(a) => Retained::SizePrefixed(a.with_checksum()),
+        }
+    }
+
+    /// Return the checksum recorded by with_checksum, if any.
+    #[cfg(feature = "checksum")]
+    pub fn checksum(&self) -> Option<u32> {
+        match self {
+            Retained::Unprefixed(a) => a.checksum(),
+            Retained::SizePrefixed(a) => a.checksum(),
+        }
+    }
+
+    /// Check that the flatbuffer bytes still match the checksum
+    /// recorded by with_checksum. Succeeds if none was recorded.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::ChecksumMismatch if the bytes changed after
+    /// the checksum was recorded.
+    #[cfg(feature = "checksum")]
+    pub fn verify_integrity(&self) -> Result<(), Error> {
+        match self {
+            Retained::Unprefixed(a) => a.verify_integrity(),
+            Retained::SizePrefixed(a) => a.verify_integrity(),
+        }
+    }
+}
+
+impl<T> Retained<T, Vec<u8>> {
     /// Return a reference to the Vec that make up the data within.
+    /// The flatbuffer starts at `head()` within it.
+    #[deprecated(
+        note = "returns the whole storage, which holds more than the flatbuffer when head() is not 0; use storage() or as_ref()"
+    )]
     pub fn as_vec(&self) -> &Vec<u8> {
         match self {
-            Retained::Unprefixed(a) => a.as_vec(),
-            Retained::SizePrefixed(a) => a.as_vec(),
+            Retained::Unprefixed(a) => a.storage(),
+            Retained::SizePrefixed(a) => a.storage(),
         }
     }
-}
 
-impl<'a, T: Follow<'a>> Into<Vec<u8>> for Retained<'a, T> {
-    fn into(self) -> Vec<u8> {
+    /// Move the verified data into reference counted storage
+    /// without re-verifying it, so that projections share it.
+    pub fn into_arc(self) -> Retained<T, Arc<[u8]>> {
         match self {
-            Retained::Unprefixed(a) => a.take(),
-            Retained::SizePrefixed(a) => a.take(),
+            Retained::Unprefixed(a) => Retained::Unprefixed(a.into_arc()),
+            Retained::SizePrefixed(a) => Retained::SizePrefixed(a.into_arc()),
         }
     }
 }
 
-impl<'a, 'b, T: Follow<'a>> Into<&'b [u8]> for &'b Retained<'a, T> {
-    fn into(self) -> &'b [u8] {
-        match self {
-            &Retained::Unprefixed(ref a) => a.into(),
-            &Retained::SizePrefixed(ref a) => a.into(),
+impl<T, B> From<FlatbufferRetained<T, B>> for Retained<T, B> {
+    fn from(retained: FlatbufferRetained<T, B>) -> Self {
+        Retained::Unprefixed(retained)
+    }
+}
+
+impl<T, B> From<SizePrefixedFlatbufferRetained<T, B>> for Retained<T, B> {
+    fn from(retained: SizePrefixedFlatbufferRetained<T, B>) -> Self {
+        Retained::SizePrefixed(retained)
+    }
+}
+
+impl<T: RetainedRoot> TryFrom<Vec<u8>> for Retained<T> {
+    type Error = RetainError<Vec<u8>, AutoDetectError>;
+    /// Detect the framing of `data` as in Retained::new_auto.
+    fn try_from(data: Vec<u8>) -> Result<Self, RetainError<Vec<u8>, AutoDetectError>> {
+        Self::try_new_auto(data)
+    }
+}
+
+impl<T> From<Retained<T>> for Vec<u8> {
+    fn from(retained: Retained<T>) -> Vec<u8> {
+        match retained {
+            Retained::Unprefixed(a) => a.into(),
+            Retained::SizePrefixed(a) => a.into(),
         }
     }
 }
 
-impl<'a, T: Follow<'a>> AsRef<[u8]> for Retained<'a, T> {
+impl<'b, T, B: StableBytes> From<&'b Retained<T, B>> for &'b [u8] {
+    fn from(retained: &'b Retained<T, B>) -> &'b [u8] {
+        match retained {
+            Retained::Unprefixed(a) => a.into(),
+            Retained::SizePrefixed(a) => a.into(),
+        }
+    }
+}
+
+impl<T, B: StableBytes> AsRef<[u8]> for Retained<T, B> {
     fn as_ref(&self) -> &[u8] {
         match self {
-            &Retained::Unprefixed(ref a) => a.as_ref(),
-            &Retained::SizePrefixed(ref a) => a.as_ref(),
+            Retained::Unprefixed(a) => a.as_ref(),
+            Retained::SizePrefixed(a) => a.as_ref(),
         }
     }
 }
 
-impl<'a, T: Follow<'a>> std::borrow::Borrow<[u8]> for Retained<'a, T> {
+impl<T, B: StableBytes> core::borrow::Borrow<[u8]> for Retained<T, B> {
     fn borrow(&self) -> &[u8] {
         match self {
-            &Retained::Unprefixed(ref a) => a.borrow(),
-            &Retained::SizePrefixed(ref a) => a.borrow(),
+            Retained::Unprefixed(a) => a.borrow(),
+            Retained::SizePrefixed(a) => a.borrow(),
         }
     }
 }
 
-impl<'a, T: Follow<'a>> std::ops::Deref for Retained<'a, T> {
+impl<T, B: StableBytes> core::ops::Deref for Retained<T, B> {
     type Target = [u8];
     fn deref(&self) -> &[u8] {
         match self {
-            &Retained::Unprefixed(ref a) => a.deref(),
-            &Retained::SizePrefixed(ref a) => a.deref(),
+            Retained::Unprefixed(a) => a.deref(),
+            Retained::SizePrefixed(a) => a.deref(),
         }
     }
 }
 
-impl<'a, T: Follow<'a>> IntoIterator for Retained<'a, T> {
+impl<T, B> IntoIterator for Retained<T, B>
+where
+    B: StableBytes + IntoIterator<Item = u8>,
+{
     type Item = u8;
-    type IntoIter = <Vec<u8> as IntoIterator>::IntoIter;
-    fn into_iter(self) -> <Vec<u8> as IntoIterator>::IntoIter {
+    type IntoIter = core::iter::Take<core::iter::Skip<B::IntoIter>>;
+    fn into_iter(self) -> core::iter::Take<core::iter::Skip<B::IntoIter>> {
         match self {
             Retained::Unprefixed(a) => a.into_iter(),
             Retained::SizePrefixed(a) => a.into_iter(),
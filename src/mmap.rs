@@ -0,0 +1,79 @@
+//! This module provides file-backed storage for retained flatbuffers.
+//! The file is mapped into memory read-only so that multi-gigabyte
+//! buffers can be verified once and then read without being copied
+//! onto the heap.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use fs4::fs_std::FileExt;
+use memmap2::{Mmap, MmapOptions};
+
+use crate::{FlatbufferRetained, SizePrefixedFlatbufferRetained};
+
+/// An unprefixed flatbuffer of type T retained in a memory-mapped file.
+pub type MmapRetained<T> = FlatbufferRetained<T, MappedFile>;
+
+/// A size-prefixed flatbuffer of type T retained in a memory-mapped file.
+pub type SizePrefixedMmapRetained<T> = SizePrefixedFlatbufferRetained<T, MappedFile>;
+
+/// A read-only, private memory mapping of a whole file.
+///
+/// The file is opened read-only, mapped copy-on-write so this process
+/// can never write through the mapping, and holds a shared advisory
+/// lock for as long as the mapping lives. Writers that take an
+/// exclusive lock on the file are therefore kept out, and a file that
+/// is already locked exclusively is refused.
+#[derive(Debug)]
+pub struct MappedFile {
+    /// The mapping of the whole file.
+    map: Mmap,
+    /// The file backing the mapping, which carries the shared lock.
+    file: File,
+}
+
+impl MappedFile {
+    /// Map the file at `path` read-only.
+    ///
+    /// # Errors
+    ///
+    /// Returns an io::Error if the file cannot be opened, locked
+    /// or mapped. A file that another handle has locked exclusively
+    /// yields an error of kind io::ErrorKind::WouldBlock.
+    ///
+    /// # Safety
+    ///
+    /// Retained flatbuffers read the mapped bytes without re-verifying
+    /// them, so the file must not be truncated or modified while the
+    /// mapping is alive. Advisory locks are only honoured by cooperating
+    /// writers; the caller must ensure no other writer touches the file.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // Called through fs4 rather than File::try_lock_shared, which
+        // needs Rust 1.89.
+        if !FileExt::try_lock_shared(&file)? {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        let map = MmapOptions::new().map_copy_read_only(&file)?;
+        Ok(MappedFile { map, file })
+    }
+
+    /// Return the file backing the mapping.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+impl std::ops::Deref for MappedFile {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+}
@@ -0,0 +1,36 @@
+//! This module holds named VerifierOptions presets so each call site
+//! can pick the safety/size tradeoff that matches where its data
+//! comes from.
+
+use flatbuffers::VerifierOptions;
+
+/// Options for buffers received from untrusted peers, such as the
+/// network. The limits are tight so that a hostile buffer cannot make
+/// the verifier walk an enormous object graph.
+pub const UNTRUSTED_NETWORK: VerifierOptions = VerifierOptions {
+    max_depth: 32,
+    max_tables: 10_000,
+    max_apparent_size: 1 << 24,
+    ignore_missing_null_terminator: false,
+};
+
+/// Options for large buffers that were produced by a trusted writer,
+/// such as snapshots loaded from local disk. The table count and
+/// apparent size are effectively unbounded so that multi-hundred-megabyte
+/// buffers verify, while the depth limit still guards the stack.
+pub const TRUSTED_LARGE_FILE: VerifierOptions = VerifierOptions {
+    max_depth: 64,
+    max_tables: usize::MAX,
+    max_apparent_size: usize::MAX,
+    ignore_missing_null_terminator: false,
+};
+
+/// Options for re-verifying part of a buffer that was already verified
+/// as a whole. Whatever limits the whole buffer passed also bound any
+/// table inside it, so none are enforced again.
+pub(crate) const ALREADY_VERIFIED: VerifierOptions = VerifierOptions {
+    max_depth: usize::MAX,
+    max_tables: usize::MAX,
+    max_apparent_size: usize::MAX,
+    ignore_missing_null_terminator: true,
+};
@@ -0,0 +1,129 @@
+//! This module provides owned handles to tables nested inside a
+//! retained flatbuffer. A handle shares the parent's storage and
+//! records where the table lives, so it can be stored on its own
+//! and read later without keeping the parent borrowed.
+
+use alloc::sync::Arc;
+use core::marker::PhantomData;
+use flatbuffers::{Follow, Verifiable, Verifier};
+
+use crate::options::ALREADY_VERIFIED;
+use crate::{RetainedRoot, SharedBytes, StableBytes};
+
+/// This struct holds a table of type U nested inside a verified
+/// flatbuffer, together with the storage of that flatbuffer.
+/// It is created by projecting from a FlatbufferRetained,
+/// SizePrefixedFlatbufferRetained or another RetainedRef, and
+/// cloning it only clones the storage handle, which is cheap
+/// for the default `Arc<[u8]>` storage.
+#[derive(Clone, Debug)]
+pub struct RetainedRef<U, B = Arc<[u8]>> {
+    /// Storage of the flatbuffer containing the table.
+    data: B,
+    /// Offset within data at which the flatbuffer starts.
+    head: usize,
+    /// Offset within data at which the flatbuffer ends.
+    end: usize,
+    /// Location of the table relative to the start of the flatbuffer.
+    loc: usize,
+    /// Phantom data to place-hold which U the table was validated as.
+    phantom: PhantomData<U>,
+}
+
+impl<U, B> RetainedRef<U, B>
+where
+    U: RetainedRoot,
+    B: StableBytes,
+{
+    /// Return the table of type U referenced by self.
+    pub fn get(&self) -> U::Table<'_> {
+        unsafe { <U::Table<'_>>::follow(self.bytes(), self.loc) }
+    }
+
+    /// Project from the table referenced by self to a table nested
+    /// within it, returning an owned handle that shares the storage.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Returns the nested table read from the table
+    ///   referenced by self, or None if it is absent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` returns a table that does not belong to this
+    /// buffer or that does not verify as a V.
+    pub fn project<V, F>(&self, f: F) -> Option<RetainedRef<V, B>>
+    where
+        V: RetainedRoot,
+        B: SharedBytes,
+        F: for<'a> FnOnce(&'a Self) -> Option<V::Table<'a>>,
+    {
+        let loc = locate::<V>(self.bytes(), f(self)?);
+        Some(RetainedRef::new(
+            self.data.clone(),
+            self.head,
+            self.end,
+            loc,
+        ))
+    }
+}
+
+impl<U, B: StableBytes> RetainedRef<U, B> {
+    /// Make a handle to the table at `loc` inside `data[head..end]`.
+    pub(crate) fn new(data: B, head: usize, end: usize, loc: usize) -> Self {
+        RetainedRef {
+            data,
+            head,
+            end,
+            loc,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Return the flatbuffer bytes within the storage.
+    fn bytes(&self) -> &[u8] {
+        &self.data.as_ref()[self.head..self.end]
+    }
+
+    /// Return the location of the table relative to the start of the
+    /// flatbuffer containing it.
+    pub fn loc(&self) -> usize {
+        self.loc
+    }
+
+    /// Return the flatbuffer containing the table.
+    pub fn buffer(&self) -> &[u8] {
+        self.bytes()
+    }
+
+    /// Return a reference to the storage shared with the parent.
+    pub fn storage(&self) -> &B {
+        &self.data
+    }
+}
+
+/// Return the location of `table` within `bytes`, after checking that
+/// the table reads from `bytes` and verifying the table as a U.
+///
+/// Generated tables expose their flatbuffers::Table as a public field,
+/// so safe code can dress one table up as another type. Verifying just
+/// the projected table keeps such a table from being misread later.
+/// `bytes` already passed the parent's verifier limits, so none are
+/// enforced here and a table the parent accepted always verifies.
+///
+/// # Panics
+///
+/// Panics if the table reads from a different buffer or does not
+/// verify as a U.
+pub(crate) fn locate<U: RetainedRoot>(bytes: &[u8], table: U::Table<'_>) -> usize {
+    let table = U::table(&table);
+    assert!(
+        core::ptr::eq(table.buf(), bytes),
+        "projected table does not belong to the retained buffer"
+    );
+    let mut v = Verifier::new(&ALREADY_VERIFIED, bytes);
+    if let Err(err) = <U::Table<'_>>::run_verifier(&mut v, table.loc()) {
+        panic!("projected table does not verify: {}", err);
+    }
+    table.loc()
+}
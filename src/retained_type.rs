@@ -0,0 +1,153 @@
+//! This module defines a macro that generates a named wrapper around a
+//! retained flatbuffer, so each root type does not need hand-written
+//! constructors and forwarding accessors.
+
+/// Generate a newtype over FlatbufferRetained, or over
+/// SizePrefixedFlatbufferRetained when the root type is marked
+/// `size_prefixed`, for a root type implementing RetainedRoot.
+///
+/// The wrapper is generic over the storage B, which defaults to
+/// `Vec<u8>`. It gets `new`, `new_with_options`, `try_new`, `get`,
+/// `inner` and `into_inner`, conversions from the wrapped type,
+/// `TryFrom<Vec<u8>>`, `From<Wrapper> for Vec<u8>`, and `AsRef<[u8]>`,
+/// `Borrow<[u8]>` and `Deref` to the flatbuffer bytes. Each line in the
+/// optional braces adds an accessor forwarding to a field of the root,
+/// carrying any attributes written before it.
+///
+/// ```ignore
+/// flatbuffers_retained::retained_root!(Monster);
+/// flatbuffers_retained::retained_type! {
+///     #[derive(Clone, Debug)]
+///     pub struct SerializedMonster(Monster) {
+///         /// The monster's remaining health.
+///         pub fn get_hp() -> i16 => hp;
+///         #[inline]
+///         pub fn name() -> Option<&str> => name;
+///     }
+/// }
+/// flatbuffers_retained::retained_type! {
+///     pub struct FramedMonster(size_prefixed Monster);
+/// }
+/// ```
+#[macro_export]
+macro_rules! retained_type {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident(size_prefixed $($table:ident)::+)
+        $({
+            $($(#[$fmeta:meta])* $fvis:vis fn $accessor:ident() -> $ret:ty => $field:ident;)*
+        })?
+        $(;)?
+    ) => {
+        $crate::retained_type! {
+            @impl [$crate::SizePrefixedFlatbufferRetained]
+            $(#[$meta])* $vis $name($($table)::+)
+            $($($(#[$fmeta])* $fvis fn $accessor() -> $ret => $field;)*)?
+        }
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident($($table:ident)::+)
+        $({
+            $($(#[$fmeta:meta])* $fvis:vis fn $accessor:ident() -> $ret:ty => $field:ident;)*
+        })?
+        $(;)?
+    ) => {
+        $crate::retained_type! {
+            @impl [$crate::FlatbufferRetained]
+            $(#[$meta])* $vis $name($($table)::+)
+            $($($(#[$fmeta])* $fvis fn $accessor() -> $ret => $field;)*)?
+        }
+    };
+    (
+        @impl [$($retained:tt)+]
+        $(#[$meta:meta])* $vis:vis $name:ident($($table:ident)::+)
+        $($(#[$fmeta:meta])* $fvis:vis fn $accessor:ident() -> $ret:ty => $field:ident;)*
+    ) => {
+        $(#[$meta])*
+        $vis struct $name<B = $crate::__private::Vec<u8>>($($retained)+<$($table)::+<'static>, B>);
+
+        impl<B: $crate::StableBytes> $name<B> {
+            /// Verify `data` as a flatbuffer and wrap it.
+            $vis fn new(data: B) -> Result<Self, $crate::__private::InvalidFlatbuffer> {
+                Ok($name(<$($retained)+<$($table)::+<'static>, B>>::new(data)?))
+            }
+
+            /// Verify `data` as a flatbuffer with custom options and wrap it.
+            $vis fn new_with_options(
+                data: B,
+                opts: &$crate::__private::VerifierOptions,
+            ) -> Result<Self, $crate::__private::InvalidFlatbuffer> {
+                Ok($name(<$($retained)+<$($table)::+<'static>, B>>::new_with_options(
+                    data, opts,
+                )?))
+            }
+
+            /// Verify `data` as a flatbuffer and wrap it, handing the
+            /// data back if it is rejected.
+            $vis fn try_new(data: B) -> Result<Self, $crate::RetainError<B>> {
+                Ok($name(<$($retained)+<$($table)::+<'static>, B>>::try_new(data)?))
+            }
+
+            /// Return a valid root from the flatbuffer stored in self.
+            $vis fn get(&self) -> $($table)::+<'_> {
+                self.0.get()
+            }
+
+            /// Return the wrapped retained flatbuffer.
+            $vis fn inner(&self) -> &$($retained)+<$($table)::+<'static>, B> {
+                &self.0
+            }
+
+            /// Deconstruct this wrapper and return the retained flatbuffer.
+            $vis fn into_inner(self) -> $($retained)+<$($table)::+<'static>, B> {
+                self.0
+            }
+
+            $(
+                $(#[$fmeta])*
+                $fvis fn $accessor(&self) -> $ret {
+                    self.0.get().$field()
+                }
+            )*
+        }
+
+        impl<B> From<$($retained)+<$($table)::+<'static>, B>> for $name<B> {
+            fn from(retained: $($retained)+<$($table)::+<'static>, B>) -> Self {
+                $name(retained)
+            }
+        }
+
+        impl TryFrom<$crate::__private::Vec<u8>> for $name {
+            type Error = $crate::RetainError;
+            fn try_from(data: $crate::__private::Vec<u8>) -> Result<Self, $crate::RetainError> {
+                Self::try_new(data)
+            }
+        }
+
+        impl From<$name> for $crate::__private::Vec<u8> {
+            fn from(wrapper: $name) -> $crate::__private::Vec<u8> {
+                wrapper.0.into()
+            }
+        }
+
+        impl<B: $crate::StableBytes> AsRef<[u8]> for $name<B> {
+            fn as_ref(&self) -> &[u8] {
+                self.0.as_ref()
+            }
+        }
+
+        impl<B: $crate::StableBytes> ::core::borrow::Borrow<[u8]> for $name<B> {
+            fn borrow(&self) -> &[u8] {
+                self.0.as_ref()
+            }
+        }
+
+        impl<B: $crate::StableBytes> ::core::ops::Deref for $name<B> {
+            type Target = [u8];
+            fn deref(&self) -> &[u8] {
+                self.0.as_ref()
+            }
+        }
+    };
+}
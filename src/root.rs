@@ -0,0 +1,42 @@
+//! This module defines the root trait that lets retained buffers
+//! name a flatbuffer table type without carrying its lifetime.
+
+use flatbuffers::{Follow, Table, Verifiable};
+
+/// A flatbuffer table type that can be followed at any lifetime.
+///
+/// Tables generated by flatc, such as `Monster<'a>`, borrow the buffer
+/// they were read from. This trait maps the table type to the same
+/// table borrowing a buffer for any lifetime `'buf`, which lets
+/// FlatbufferRetained hand out `Monster<'_>` tied only to the borrow of
+/// the retained value itself.
+///
+/// Implement it with the retained_root! macro.
+pub trait RetainedRoot {
+    /// The table type reading from a buffer borrowed for `'buf`.
+    type Table<'buf>: Follow<'buf, Inner = Self::Table<'buf>> + Verifiable;
+
+    /// Return the flatbuffers::Table that `table` reads from, which
+    /// records the buffer and the table's location within it.
+    fn table<'buf>(table: &Self::Table<'buf>) -> Table<'buf>;
+}
+
+/// Implement RetainedRoot for one or more flatc generated table types.
+///
+/// ```ignore
+/// flatbuffers_retained::retained_root!(Monster, my_game::sample::Weapon);
+/// ```
+#[macro_export]
+macro_rules! retained_root {
+    ($($($table:ident)::+),+ $(,)?) => {
+        $(
+            impl<'x> $crate::RetainedRoot for $($table)::+<'x> {
+                type Table<'buf> = $($table)::+<'buf>;
+
+                fn table<'buf>(table: &Self::Table<'buf>) -> $crate::__private::Table<'buf> {
+                    table._tab
+                }
+            }
+        )+
+    };
+}
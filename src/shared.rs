@@ -0,0 +1,187 @@
+//! This module focuses on sharing a verified flatbuffer between many
+//! owners, such as worker threads, without copying or re-verifying it.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use flatbuffers::{root_unchecked, size_prefixed_root_unchecked};
+
+use crate::retained_ref::{locate, RetainedRef};
+use crate::{FlatbufferRetained, Retained, RetainedRoot, SizePrefixedFlatbufferRetained};
+
+/// This struct holds a verified flatbuffer of type T, either unprefixed
+/// or size-prefixed, in reference counted storage. Cloning it only
+/// bumps the reference count.
+///
+/// SharedRetained is Send and Sync whatever T is: it owns nothing but
+/// the shared bytes, and every call to `get` reads a fresh table from
+/// them on the calling thread.
+#[derive(Debug)]
+pub struct SharedRetained<T> {
+    /// Storage of the verified flatbuffer.
+    data: Arc<[u8]>,
+    /// Offset within data at which the flatbuffer starts.
+    head: usize,
+    /// Offset within data at which the flatbuffer ends.
+    end: usize,
+    /// Whether the flatbuffer is prefixed by its size.
+    size_prefixed: bool,
+    /// Phantom data to place-hold which T data was validated for.
+    /// A function pointer keeps T out of the Send and Sync bounds.
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T: RetainedRoot> SharedRetained<T> {
+    /// Return a valid root of type T from the flatbuffer
+    /// buffer stored in self.
+    pub fn get(&self) -> T::Table<'_> {
+        if self.size_prefixed {
+            unsafe { size_prefixed_root_unchecked::<T::Table<'_>>(self.bytes()) }
+        } else {
+            unsafe { root_unchecked::<T::Table<'_>>(self.bytes()) }
+        }
+    }
+
+    /// Project from the root to a table nested within it, returning an
+    /// owned handle that shares the storage of self.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Returns the nested table read from the root of self,
+    ///   or None if it is absent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` returns a table that does not belong to this
+    /// buffer or that does not verify as a U.
+    pub fn project<U, F>(&self, f: F) -> Option<RetainedRef<U>>
+    where
+        U: RetainedRoot,
+        F: for<'a> FnOnce(&'a Self) -> Option<U::Table<'a>>,
+    {
+        let loc = locate::<U>(self.bytes(), f(self)?);
+        Some(RetainedRef::new(
+            self.data.clone(),
+            self.head,
+            self.end,
+            loc,
+        ))
+    }
+}
+
+impl<T> SharedRetained<T> {
+    /// Wrap shared data whose flatbuffer spans `head..end`.
+    fn from_parts(data: Arc<[u8]>, head: usize, end: usize, size_prefixed: bool) -> Self {
+        SharedRetained {
+            data,
+            head,
+            end,
+            size_prefixed,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Return the flatbuffer bytes within the storage.
+    fn bytes(&self) -> &[u8] {
+        &self.data[self.head..self.end]
+    }
+
+    /// Return whether the flatbuffer is prefixed by its size.
+    pub fn is_size_prefixed(&self) -> bool {
+        self.size_prefixed
+    }
+
+    /// Return an iterator to traverse over the contained data.
+    pub fn iter(&self) -> core::slice::Iter<'_, u8> {
+        self.bytes().iter()
+    }
+
+    /// Return a reference to the storage shared by all clones.
+    /// The flatbuffer is the part of it returned by `as_ref()`.
+    pub fn storage(&self) -> &Arc<[u8]> {
+        &self.data
+    }
+}
+
+impl<T> Clone for SharedRetained<T> {
+    fn clone(&self) -> Self {
+        SharedRetained::from_parts(self.data.clone(), self.head, self.end, self.size_prefixed)
+    }
+}
+
+impl<T> From<FlatbufferRetained<T, Arc<[u8]>>> for SharedRetained<T> {
+    fn from(retained: FlatbufferRetained<T, Arc<[u8]>>) -> Self {
+        let (head, end) = (retained.head(), retained.head() + retained.len());
+        SharedRetained::from_parts(retained.take(), head, end, false)
+    }
+}
+
+impl<T> From<FlatbufferRetained<T, Vec<u8>>> for SharedRetained<T> {
+    /// Move the verified flatbuffer into shared storage without
+    /// re-verifying it.
+    fn from(retained: FlatbufferRetained<T, Vec<u8>>) -> Self {
+        let data: Vec<u8> = retained.into();
+        let end = data.len();
+        SharedRetained::from_parts(data.into(), 0, end, false)
+    }
+}
+
+impl<T> From<SizePrefixedFlatbufferRetained<T, Arc<[u8]>>> for SharedRetained<T> {
+    fn from(retained: SizePrefixedFlatbufferRetained<T, Arc<[u8]>>) -> Self {
+        let (head, end) = (retained.head(), retained.head() + retained.len());
+        SharedRetained::from_parts(retained.take(), head, end, true)
+    }
+}
+
+impl<T> From<SizePrefixedFlatbufferRetained<T, Vec<u8>>> for SharedRetained<T> {
+    /// Move the verified flatbuffer into shared storage without
+    /// re-verifying it.
+    fn from(retained: SizePrefixedFlatbufferRetained<T, Vec<u8>>) -> Self {
+        let data: Vec<u8> = retained.into();
+        let end = data.len();
+        SharedRetained::from_parts(data.into(), 0, end, true)
+    }
+}
+
+impl<T> From<Retained<T, Vec<u8>>> for SharedRetained<T> {
+    fn from(retained: Retained<T, Vec<u8>>) -> Self {
+        match retained {
+            Retained::Unprefixed(a) => a.into(),
+            Retained::SizePrefixed(a) => a.into(),
+        }
+    }
+}
+
+impl<T> From<Retained<T, Arc<[u8]>>> for SharedRetained<T> {
+    fn from(retained: Retained<T, Arc<[u8]>>) -> Self {
+        match retained {
+            Retained::Unprefixed(a) => a.into(),
+            Retained::SizePrefixed(a) => a.into(),
+        }
+    }
+}
+
+impl<'b, T> From<&'b SharedRetained<T>> for &'b [u8] {
+    fn from(retained: &'b SharedRetained<T>) -> &'b [u8] {
+        retained.bytes()
+    }
+}
+
+impl<T> AsRef<[u8]> for SharedRetained<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.bytes()
+    }
+}
+
+impl<T> core::borrow::Borrow<[u8]> for SharedRetained<T> {
+    fn borrow(&self) -> &[u8] {
+        self.bytes()
+    }
+}
+
+impl<T> core::ops::Deref for SharedRetained<T> {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        self.bytes()
+    }
+}
@@ -2,24 +2,46 @@
 //! with 32 bit size-prefixes. It allows validation of Flatbuffer buffers
 //! once at initialization time then safely use them, unchecked, later.
 
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
 use flatbuffers::{
-    size_prefixed_root_unchecked, Follow, ForwardsUOffset, InvalidFlatbuffer, SkipSizePrefix,
-    Verifiable, Verifier, VerifierOptions,
+    size_prefixed_root_unchecked, FlatBufferBuilder, ForwardsUOffset, InvalidFlatbuffer,
+    SkipSizePrefix, Verifiable, Verifier, VerifierOptions, SIZE_SIZEPREFIX,
 };
-use std::marker::PhantomData;
+
+use crate::batch::{retain_batch, BatchMode, BatchReport};
+#[cfg(any(feature = "zstd", feature = "lz4"))]
+use crate::compress;
+#[cfg(feature = "envelope")]
+use crate::envelope::{self, EnvelopeKey, ENVELOPE_HEADER_LEN};
+#[cfg(feature = "checksum")]
+use crate::error::check_checksum;
+use crate::error::{check_identifier, Error, RetainError};
+use crate::retained_ref::{locate, RetainedRef};
+use crate::strict;
+use crate::trim::TrimReport;
+#[cfg(feature = "cache")]
+use crate::VerificationCache;
+use crate::{RetainedRoot, SharedBytes, StableBytes};
 
 /// This struct holds data backing an size-prefixed flatbuffer.
 /// It is not possible to create this struct without a valid
 /// flatbuffer of type T.
 #[derive(Clone, Debug)]
-pub struct SizePrefixedFlatbufferRetained<'a, T: Follow<'a>> {
-    data: Vec<u8>,
-    phantom: PhantomData<&'a T>,
+pub struct SizePrefixedFlatbufferRetained<T, B = Vec<u8>> {
+    data: B,
+    head: usize,
+    end: usize,
+    phantom: PhantomData<T>,
+    #[cfg(feature = "checksum")]
+    checksum: Option<u32>,
 }
 
-impl<'a, T> SizePrefixedFlatbufferRetained<'a, T>
+impl<T, B> SizePrefixedFlatbufferRetained<T, B>
 where
-    T: Follow<'a> + Verifiable,
+    T: RetainedRoot,
+    B: StableBytes,
 {
     /// Make a new FlatbufferRetained class of type T from
     /// a size-prefixed byte buffer.
@@ -27,82 +49,871 @@ where
     /// # Arguments
     ///
     /// * `data` - The data that represents a flatbuffer of type T,
-    ///            prefixed by size.
+    ///   prefixed by size.
     ///
     /// # Errors
     ///
     /// May return any InvalidFlatbuffer error from run_verifier
     /// when it parses the data.
-    pub fn new(data: Vec<u8>) -> Result<Self, InvalidFlatbuffer> {
-        let opts = VerifierOptions::default();
-        let mut v = Verifier::new(&opts, &data);
-        <SkipSizePrefix<ForwardsUOffset<T>>>::run_verifier(&mut v, 0)?;
-        Ok(SizePrefixedFlatbufferRetained {
-            data,
-            phantom: PhantomData::default(),
+    pub fn new(data: B) -> Result<Self, InvalidFlatbuffer> {
+        Self::new_with_options(data, &VerifierOptions::default())
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed byte buffer, verifying it with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn new_with_options(data: B, opts: &VerifierOptions) -> Result<Self, InvalidFlatbuffer> {
+        Self::verify(data.as_ref(), opts)?;
+        // Safety: the data was just verified.
+        Ok(unsafe { Self::new_unchecked(data) })
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed byte buffer, handing the data back if it is rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    ///
+    /// # Errors
+    ///
+    /// Returns a RetainError holding the InvalidFlatbuffer error from
+    /// run_verifier together with the rejected data.
+    pub fn try_new(data: B) -> Result<Self, RetainError<B>> {
+        Self::try_new_with_options(data, &VerifierOptions::default())
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed byte buffer, verifying it with custom options
+    /// and handing the data back if it is rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns a RetainError holding the InvalidFlatbuffer error from
+    /// run_verifier together with the rejected data.
+    pub fn try_new_with_options(data: B, opts: &VerifierOptions) -> Result<Self, RetainError<B>> {
+        match Self::verify(data.as_ref(), opts) {
+            // Safety: the data was just verified.
+            Ok(()) => Ok(unsafe { Self::new_unchecked(data) }),
+            Err(err) => Err(RetainError::new(err, data)),
+        }
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed byte buffer, skipping the verifier when `cache`
+    /// remembers identical bytes passing it for the same T and options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    /// * `cache` - The record of buffers that already passed verification.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    #[cfg(feature = "cache")]
+    pub fn new_cached(
+        data: B,
+        opts: &VerifierOptions,
+        cache: &VerificationCache,
+    ) -> Result<Self, InvalidFlatbuffer>
+    where
+        T::Table<'static>: 'static,
+    {
+        let root = core::any::TypeId::of::<T::Table<'static>>();
+        cache.verify(data.as_ref(), root, true, opts, Self::verify)?;
+        // Safety: the data was verified, now or by an earlier call.
+        Ok(unsafe { Self::new_unchecked(data) })
+    }
+
+    /// Make a FlatbufferRetained class of type T from each of many
+    /// size-prefixed byte buffers. With the `rayon` feature the buffers
+    /// are verified in parallel on the global thread pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffers` - The data, each of which should represent a flatbuffer of type T.
+    /// * `mode` - Whether to stop verifying after the first error.
+    pub fn new_batch<I>(buffers: I, mode: BatchMode) -> BatchReport<Self, B>
+    where
+        I: IntoIterator<Item = B>,
+        Self: Send,
+        B: Send,
+    {
+        Self::new_batch_with_options(buffers, &VerifierOptions::default(), mode)
+    }
+
+    /// Make a FlatbufferRetained class of type T from each of many
+    /// size-prefixed byte buffers, verifying them with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffers` - The data, each of which should represent a flatbuffer of type T.
+    /// * `opts` - The limits the verifier enforces while parsing each buffer.
+    /// * `mode` - Whether to stop verifying after the first error.
+    pub fn new_batch_with_options<I>(
+        buffers: I,
+        opts: &VerifierOptions,
+        mode: BatchMode,
+    ) -> BatchReport<Self, B>
+    where
+        I: IntoIterator<Item = B>,
+        Self: Send,
+        B: Send,
+    {
+        retain_batch(buffers.into_iter().collect(), mode, |data| {
+            Self::try_new_with_options(data, opts)
         })
     }
 
+    /// Run the verifier for a root of type T over `data`.
+    pub(crate) fn verify(data: &[u8], opts: &VerifierOptions) -> Result<(), InvalidFlatbuffer> {
+        let mut v = Verifier::new(opts, data);
+        <SkipSizePrefix<ForwardsUOffset<T::Table<'_>>>>::run_verifier(&mut v, 0)
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed byte buffer whose size prefix must declare exactly
+    /// the bytes after it. The declared region is taken as the whole
+    /// flatbuffer, so fields a newer schema added are accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::SizePrefixMismatch if the size prefix is wrong,
+    /// or any InvalidFlatbuffer error from run_verifier when it parses
+    /// the data.
+    pub fn new_strict(data: B) -> Result<Self, Error> {
+        Self::new_strict_with_options(data, &VerifierOptions::default())
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed byte buffer whose size prefix must declare exactly
+    /// the bytes after it, verifying it with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::SizePrefixMismatch if the size prefix is wrong,
+    /// or any InvalidFlatbuffer error from run_verifier when it parses
+    /// the data.
+    pub fn new_strict_with_options(data: B, opts: &VerifierOptions) -> Result<Self, Error> {
+        let bytes = data.as_ref();
+        Self::verify(bytes, opts)?;
+        strict::check_declared_size(bytes)?;
+        // Safety: the data was just verified.
+        Ok(unsafe { Self::new_unchecked(data) })
+    }
+
+    /// Make a new FlatbufferRetained class of type T from the region of
+    /// a size-prefixed byte buffer that its size prefix declares. Bytes
+    /// past that region are not verified and are left in `trailer()`,
+    /// so `get()` and `as_ref()` only see the declared region.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that starts with a flatbuffer of type T,
+    ///   prefixed by size.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::SizePrefixMismatch if the size prefix declares more
+    /// bytes than follow it, or any InvalidFlatbuffer error from
+    /// run_verifier when it parses the declared region.
+    pub fn new_declared(data: B) -> Result<Self, Error> {
+        Self::new_declared_with_options(data, &VerifierOptions::default())
+    }
+
+    /// Make a new FlatbufferRetained class of type T from the region of
+    /// a size-prefixed byte buffer that its size prefix declares,
+    /// verifying it with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that starts with a flatbuffer of type T,
+    ///   prefixed by size.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::SizePrefixMismatch if the size prefix declares more
+    /// bytes than follow it, or any InvalidFlatbuffer error from
+    /// run_verifier when it parses the declared region.
+    pub fn new_declared_with_options(data: B, opts: &VerifierOptions) -> Result<Self, Error> {
+        let bytes = data.as_ref();
+        let actual = bytes.len().saturating_sub(SIZE_SIZEPREFIX);
+        let end = match strict::declared_size(bytes) {
+            Some(declared) if declared <= actual => SIZE_SIZEPREFIX + declared,
+            declared => {
+                return Err(Error::SizePrefixMismatch {
+                    declared: declared.unwrap_or(0),
+                    actual,
+                })
+            }
+        };
+        Self::verify(&bytes[..end], opts)?;
+        // Safety: the declared region was just verified.
+        Ok(unsafe { Self::from_parts(data, 0, end) })
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed byte buffer that must carry the given file identifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    /// * `identifier` - The 4 byte file identifier the data must carry,
+    ///   such as the `*_IDENTIFIER` constant generated by flatc.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::IdentifierMismatch if the identifier differs,
+    /// otherwise any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `identifier` is not 4 bytes long.
+    pub fn new_with_identifier(data: B, identifier: &str) -> Result<Self, Error> {
+        Self::new_with_identifier_and_options(data, identifier, &VerifierOptions::default())
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed byte buffer that must carry the given file identifier,
+    /// verifying it with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    /// * `identifier` - The 4 byte file identifier the data must carry.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::IdentifierMismatch if the identifier differs,
+    /// otherwise any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `identifier` is not 4 bytes long.
+    pub fn new_with_identifier_and_options(
+        data: B,
+        identifier: &str,
+        opts: &VerifierOptions,
+    ) -> Result<Self, Error> {
+        check_identifier(data.as_ref(), identifier, true)?;
+        Ok(Self::new_with_options(data, opts)?)
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed flatbuffer embedded at `offset` inside `data`,
+    /// such as behind a custom header.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data containing a flatbuffer of type T.
+    /// * `offset` - The offset within data at which the flatbuffer starts.
+    /// * `len` - The length of the flatbuffer, or None if it extends
+    ///   to the end of data.
+    ///
+    /// # Errors
+    ///
+    /// Returns InvalidFlatbuffer::RangeOutOfBounds if the region does
+    /// not fit within data, otherwise any InvalidFlatbuffer error from
+    /// run_verifier when it parses the region.
+    pub fn new_at(data: B, offset: usize, len: Option<usize>) -> Result<Self, InvalidFlatbuffer> {
+        Self::new_at_with_options(data, offset, len, &VerifierOptions::default())
+    }
+
+    /// Make a new FlatbufferRetained class of type T from
+    /// a size-prefixed flatbuffer embedded at `offset` inside `data`,
+    /// verifying it with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data containing a flatbuffer of type T.
+    /// * `offset` - The offset within data at which the flatbuffer starts.
+    /// * `len` - The length of the flatbuffer, or None if it extends
+    ///   to the end of data.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns InvalidFlatbuffer::RangeOutOfBounds if the region does
+    /// not fit within data, otherwise any InvalidFlatbuffer error from
+    /// run_verifier when it parses the region.
+    pub fn new_at_with_options(
+        data: B,
+        offset: usize,
+        len: Option<usize>,
+        opts: &VerifierOptions,
+    ) -> Result<Self, InvalidFlatbuffer> {
+        let end = match len {
+            Some(len) => offset.saturating_add(len),
+            None => data.as_ref().len().max(offset),
+        };
+        let region = data
+            .as_ref()
+            .get(offset..end)
+            .ok_or(InvalidFlatbuffer::RangeOutOfBounds {
+                range: offset..end,
+                error_trace: Default::default(),
+            })?;
+        Self::verify(region, opts)?;
+        // Safety: the region between offset and end was just verified.
+        Ok(unsafe { Self::from_parts(data, offset, end) })
+    }
+
+    /// Make a new FlatbufferRetained class of type T from a finished
+    /// FlatBufferBuilder, taking ownership of its storage instead of
+    /// copying the finished data out of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - A builder on which `finish_size_prefixed` was called with a
+    ///   root of type T.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data.
+    pub fn from_builder(builder: FlatBufferBuilder<'_>) -> Result<Self, InvalidFlatbuffer>
+    where
+        B: From<Vec<u8>>,
+    {
+        let (data, head) = builder.collapse();
+        Self::verify(&data[head..], &VerifierOptions::default())?;
+        // Safety: the data after head was just verified.
+        let end = data.len();
+        Ok(unsafe { Self::from_parts(data.into(), head, end) })
+    }
+
+    /// Make a new FlatbufferRetained class of type T from a finished
+    /// FlatBufferBuilder without copying or verifying its data.
+    /// The data is still verified when debug_assertions are enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - A builder on which `finish_size_prefixed` was called with a
+    ///   root of type T.
+    ///
+    /// # Safety
+    ///
+    /// The builder must hold a finished, size-prefixed flatbuffer
+    /// whose root is a T, as produced by `finish_size_prefixed` with offsets
+    /// returned by the generated create functions.
+    pub unsafe fn from_builder_unchecked(builder: FlatBufferBuilder<'_>) -> Self
+    where
+        B: From<Vec<u8>>,
+    {
+        let (data, head) = builder.collapse();
+        debug_assert_eq!(
+            Self::verify(&data[head..], &VerifierOptions::default()),
+            Ok(())
+        );
+        let end = data.len();
+        Self::from_parts(data.into(), head, end)
+    }
+
+    /// Load a FlatbufferRetained class of type T from an envelope
+    /// written by to_envelope. The verifier is skipped when the envelope
+    /// was sealed under `key` for a size-prefixed T, and run otherwise.
+    /// The flatbuffer starts at ENVELOPE_HEADER_LEN within the storage.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The envelope holding a flatbuffer of type T.
+    /// * `key` - The secret and schema the envelope should be sealed with.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::MalformedEnvelope if `data` is not an envelope,
+    /// or any InvalidFlatbuffer error from run_verifier when the
+    /// envelope is not authentic and the flatbuffer is invalid.
+    #[cfg(feature = "envelope")]
+    pub fn from_envelope(data: B, key: &EnvelopeKey) -> Result<Self, Error> {
+        Self::from_envelope_with_options(data, key, &VerifierOptions::default())
+    }
+
+    /// Load a FlatbufferRetained class of type T from an envelope
+    /// written by to_envelope, verifying it with custom options when
+    /// the envelope is not authentic.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The envelope holding a flatbuffer of type T.
+    /// * `key` - The secret and schema the envelope should be sealed with.
+    /// * `opts` - The limits the verifier enforces if it has to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::MalformedEnvelope if `data` is not an envelope,
+    /// or any InvalidFlatbuffer error from run_verifier when the
+    /// envelope is not authentic and the flatbuffer is invalid.
+    #[cfg(feature = "envelope")]
+    pub fn from_envelope_with_options(
+        data: B,
+        key: &EnvelopeKey,
+        opts: &VerifierOptions,
+    ) -> Result<Self, Error> {
+        let root = core::any::type_name::<T::Table<'static>>();
+        if !envelope::open(data.as_ref(), root, true, key)? {
+            Self::verify(&data.as_ref()[ENVELOPE_HEADER_LEN..], opts)?;
+        }
+        let end = data.as_ref().len();
+        // Safety: the flatbuffer was either just verified or sealed by
+        // to_envelope after verification, under the caller's key.
+        Ok(unsafe { Self::from_parts(data, ENVELOPE_HEADER_LEN, end) })
+    }
+
+    /// Wrap the flatbuffer in an envelope sealed under `key`, so that
+    /// from_envelope can load it again without re-verifying it.
+    #[cfg(feature = "envelope")]
+    pub fn to_envelope(&self, key: &EnvelopeKey) -> Vec<u8> {
+        let root = core::any::type_name::<T::Table<'static>>();
+        envelope::seal(self.bytes(), root, true, key)
+    }
+
+    /// Load a FlatbufferRetained class of type T from a zstd
+    /// frame holding a size-prefixed flatbuffer, such as one written by to_zstd.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The compressed data of a flatbuffer of type T.
+    /// * `max_size` - The most bytes the frame may decompress to.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::DecompressedTooLarge if the frame expands past
+    /// `max_size`, Error::Decompression if it is not a valid zstd
+    /// frame or asks for a window larger than `max_size`, or any
+    /// InvalidFlatbuffer error from run_verifier when
+    /// it parses the decompressed data.
+    #[cfg(feature = "zstd")]
+    pub fn from_zstd(frame: &[u8], max_size: usize) -> Result<Self, Error>
+    where
+        B: From<Vec<u8>>,
+    {
+        Self::from_zstd_with_options(frame, max_size, &VerifierOptions::default())
+    }
+
+    /// Load a FlatbufferRetained class of type T from a zstd
+    /// frame holding a size-prefixed flatbuffer, verifying it with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The compressed data of a flatbuffer of type T.
+    /// * `max_size` - The most bytes the frame may decompress to.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::DecompressedTooLarge if the frame expands past
+    /// `max_size`, Error::Decompression if it is not a valid zstd
+    /// frame or asks for a window larger than `max_size`, or any
+    /// InvalidFlatbuffer error from run_verifier when
+    /// it parses the decompressed data.
+    #[cfg(feature = "zstd")]
+    pub fn from_zstd_with_options(
+        frame: &[u8],
+        max_size: usize,
+        opts: &VerifierOptions,
+    ) -> Result<Self, Error>
+    where
+        B: From<Vec<u8>>,
+    {
+        let data = compress::unzstd(frame, max_size)?;
+        Self::verify(&data, opts)?;
+        // Safety: the data was just verified.
+        Ok(unsafe { Self::new_unchecked(data.into()) })
+    }
+
+    /// Load a FlatbufferRetained class of type T from an lz4
+    /// frame holding a size-prefixed flatbuffer, such as one written by to_lz4.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The compressed data of a flatbuffer of type T.
+    /// * `max_size` - The most bytes the frame may decompress to.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::DecompressedTooLarge if the frame expands past
+    /// `max_size`, Error::Decompression if it is not a valid lz4
+    /// frame, or any InvalidFlatbuffer error from run_verifier when
+    /// it parses the decompressed data.
+    #[cfg(feature = "lz4")]
+    pub fn from_lz4(frame: &[u8], max_size: usize) -> Result<Self, Error>
+    where
+        B: From<Vec<u8>>,
+    {
+        Self::from_lz4_with_options(frame, max_size, &VerifierOptions::default())
+    }
+
+    /// Load a FlatbufferRetained class of type T from an lz4
+    /// frame holding a size-prefixed flatbuffer, verifying it with custom options.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The compressed data of a flatbuffer of type T.
+    /// * `max_size` - The most bytes the frame may decompress to.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::DecompressedTooLarge if the frame expands past
+    /// `max_size`, Error::Decompression if it is not a valid lz4
+    /// frame, or any InvalidFlatbuffer error from run_verifier when
+    /// it parses the decompressed data.
+    #[cfg(feature = "lz4")]
+    pub fn from_lz4_with_options(
+        frame: &[u8],
+        max_size: usize,
+        opts: &VerifierOptions,
+    ) -> Result<Self, Error>
+    where
+        B: From<Vec<u8>>,
+    {
+        let data = compress::unlz4(frame, max_size)?;
+        Self::verify(&data, opts)?;
+        // Safety: the data was just verified.
+        Ok(unsafe { Self::new_unchecked(data.into()) })
+    }
+
+    /// Compress the flatbuffer into a zstd frame at compression `level`,
+    /// which from_zstd can load again.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error the zstd encoder reports.
+    #[cfg(feature = "zstd")]
+    pub fn to_zstd(&self, level: i32) -> std::io::Result<Vec<u8>> {
+        compress::zstd(self.bytes(), level)
+    }
+
+    /// Compress the flatbuffer into an lz4 frame, which from_lz4 can
+    /// load again.
+    #[cfg(feature = "lz4")]
+    pub fn to_lz4(&self) -> Vec<u8> {
+        compress::lz4(self.bytes())
+    }
+
     /// Return a valid root of type T from the flatbuffer
     /// buffer stored in self.
-    pub fn get(&'a self) -> <T as Follow<'a>>::Inner {
-        unsafe { size_prefixed_root_unchecked::<T>(&self.data) }
+    pub fn get(&self) -> T::Table<'_> {
+        #[cfg(all(feature = "checksum", debug_assertions))]
+        if let Err(err) = self.verify_integrity() {
+            panic!("{}", err);
+        }
+        unsafe { size_prefixed_root_unchecked::<T::Table<'_>>(self.bytes()) }
+    }
+
+    /// Report how much memory trim would save, without copying.
+    pub fn trim_report(&self) -> TrimReport {
+        TrimReport {
+            before: self.data.as_ref().len(),
+            after: self.end - self.head,
+        }
+    }
+
+    /// Copy the flatbuffer and its size prefix into a new Vec holding
+    /// nothing else, dropping any header, trailer and builder slack
+    /// around them. Every byte of the flatbuffer is kept, including
+    /// bytes only reachable through fields unknown to T.
+    pub fn trim(&self) -> SizePrefixedFlatbufferRetained<T, Vec<u8>> {
+        // Safety: the flatbuffer bytes were verified and verification
+        // does not depend on what surrounds them.
+        unsafe { SizePrefixedFlatbufferRetained::new_unchecked(self.bytes().to_vec()) }
+    }
+
+    /// Rebuild the flatbuffer by copying its root into a fresh
+    /// FlatBufferBuilder, which also drops unreachable regions inside
+    /// it. Fields unknown to T's schema are dropped as well. The result
+    /// is trimmed, so its storage holds only the new flatbuffer.
+    ///
+    /// Without a schema the reachable ranges of a buffer cannot be
+    /// found, so this takes the place of compacting a buffer in place;
+    /// `trim` covers the bytes around the flatbuffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Writes the root of its first argument into the builder
+    ///   and calls `finish_size_prefixed` on it, for example by way of the
+    ///   generated object API.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the rebuilt data.
+    pub fn rebuild<F>(
+        &self,
+        f: F,
+    ) -> Result<SizePrefixedFlatbufferRetained<T, Vec<u8>>, InvalidFlatbuffer>
+    where
+        F: FnOnce(&Self, &mut FlatBufferBuilder),
+    {
+        let mut builder = FlatBufferBuilder::new();
+        f(self, &mut builder);
+        SizePrefixedFlatbufferRetained::<T, Vec<u8>>::from_builder(builder)
+            .map(|rebuilt| rebuilt.trim())
+    }
+
+    /// Project from the root to a table nested within it, returning an
+    /// owned handle that shares the storage of self and reads the table
+    /// without re-verifying it. The storage must be shared rather than
+    /// copied, so move `Vec<u8>` storage into an `Arc<[u8]>` with
+    /// `into_arc` first.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Returns the nested table read from the root of self,
+    ///   or None if it is absent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` returns a table that does not belong to this
+    /// buffer or that does not verify as a U.
+    pub fn project<U, F>(&self, f: F) -> Option<RetainedRef<U, B>>
+    where
+        U: RetainedRoot,
+        B: SharedBytes,
+        F: for<'a> FnOnce(&'a Self) -> Option<U::Table<'a>>,
+    {
+        let loc = locate::<U>(self.bytes(), f(self)?);
+        Some(RetainedRef::new(
+            self.data.clone(),
+            self.head,
+            self.end,
+            loc,
+        ))
     }
 }
 
-impl<'a, T: Follow<'a>> SizePrefixedFlatbufferRetained<'a, T> {
-    /// Return an iterator to traverse over the contained Vec.
-    pub fn iter(&self) -> std::slice::Iter<u8> {
-        self.data.iter()
+impl<T, B: StableBytes> SizePrefixedFlatbufferRetained<T, B> {
+    /// Wrap data without verifying it.
+    ///
+    /// # Safety
+    ///
+    /// `data` must already have passed the verifier for a root of type T.
+    pub(crate) unsafe fn new_unchecked(data: B) -> Self {
+        let end = data.as_ref().len();
+        Self::from_parts(data, 0, end)
+    }
+
+    /// Wrap data whose flatbuffer spans `head..end` without verifying it.
+    ///
+    /// # Safety
+    ///
+    /// `data[head..end]` must already have passed the verifier for a root of type T.
+    pub(crate) unsafe fn from_parts(data: B, head: usize, end: usize) -> Self {
+        SizePrefixedFlatbufferRetained {
+            data,
+            head,
+            end,
+            phantom: PhantomData,
+            #[cfg(feature = "checksum")]
+            checksum: None,
+        }
+    }
+
+    /// Record a checksum of the flatbuffer bytes so that changes made
+    /// to them after verification can be detected by verify_integrity.
+    /// With debug_assertions, get also re-checks it on every call.
+    #[cfg(feature = "checksum")]
+    pub fn with_checksum(mut self) -> Self {
+        self.checksum = Some(crc32fast::hash(self.bytes()));
+        self
     }
 
-    /// Deconstruct this class and return the Vec that
-    /// made up the data within it.
-    pub fn take(self) -> Vec<u8> {
+    /// Return the checksum recorded by with_checksum, if any.
+    #[cfg(feature = "checksum")]
+    pub fn checksum(&self) -> Option<u32> {
+        self.checksum
+    }
+
+    /// Check that the flatbuffer bytes still match the checksum
+    /// recorded by with_checksum. Succeeds if none was recorded.
+    ///
+    /// # Errors
+    ///
+    /// Returns Error::ChecksumMismatch if the bytes changed after
+    /// the checksum was recorded.
+    #[cfg(feature = "checksum")]
+    pub fn verify_integrity(&self) -> Result<(), Error> {
+        check_checksum(self.bytes(), self.checksum)
+    }
+
+    /// Return the flatbuffer bytes within the storage.
+    fn bytes(&self) -> &[u8] {
+        &self.data.as_ref()[self.head..self.end]
+    }
+
+    /// Return an iterator to traverse over the contained data.
+    pub fn iter(&self) -> core::slice::Iter<'_, u8> {
+        self.bytes().iter()
+    }
+
+    /// Return the offset within the storage at which the flatbuffer
+    /// starts. This is non-zero for data taken from a FlatBufferBuilder
+    /// or embedded behind a header.
+    pub fn head(&self) -> usize {
+        self.head
+    }
+
+    /// Return the bytes in the storage before the flatbuffer,
+    /// such as a custom header.
+    pub fn header(&self) -> &[u8] {
+        &self.data.as_ref()[..self.head]
+    }
+
+    /// Return the bytes in the storage after the flatbuffer.
+    pub fn trailer(&self) -> &[u8] {
+        &self.data.as_ref()[self.end..]
+    }
+
+    /// Deconstruct this class and return the storage that
+    /// made up the data within it. The flatbuffer starts at
+    /// `head()` within the returned storage and is followed by
+    /// `trailer()`.
+    pub fn take(self) -> B {
         self.data
     }
 
+    /// Return a reference to the storage that makes up the data within.
+    /// The flatbuffer starts at `head()` within it.
+    pub fn storage(&self) -> &B {
+        &self.data
+    }
+}
+
+impl<T> SizePrefixedFlatbufferRetained<T, Vec<u8>> {
     /// Return a reference to the Vec that make up the data within.
+    /// The flatbuffer starts at `head()` within it.
+    #[deprecated(
+        note = "returns the whole storage, which holds more than the flatbuffer when head() is not 0; use storage() or as_ref()"
+    )]
     pub fn as_vec(&self) -> &Vec<u8> {
         &self.data
     }
+
+    /// Move the verified data into reference counted storage
+    /// without re-verifying it, so that projections share it.
+    pub fn into_arc(self) -> SizePrefixedFlatbufferRetained<T, Arc<[u8]>> {
+        SizePrefixedFlatbufferRetained {
+            data: self.data.into(),
+            head: self.head,
+            end: self.end,
+            phantom: PhantomData,
+            #[cfg(feature = "checksum")]
+            checksum: self.checksum,
+        }
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<T> SizePrefixedFlatbufferRetained<T, Vec<u8>> {
+    /// Move the verified data into a bytes::Bytes without
+    /// copying or re-verifying it.
+    pub fn into_bytes(self) -> SizePrefixedFlatbufferRetained<T, bytes::Bytes> {
+        SizePrefixedFlatbufferRetained {
+            data: self.data.into(),
+            head: self.head,
+            end: self.end,
+            phantom: PhantomData,
+            #[cfg(feature = "checksum")]
+            checksum: self.checksum,
+        }
+    }
 }
 
-impl<'a, T: Follow<'a>> Into<Vec<u8>> for SizePrefixedFlatbufferRetained<'a, T> {
-    fn into(self) -> Vec<u8> {
-        self.take()
+#[cfg(feature = "bytes")]
+impl<T> From<SizePrefixedFlatbufferRetained<T, bytes::Bytes>> for bytes::Bytes {
+    fn from(retained: SizePrefixedFlatbufferRetained<T, bytes::Bytes>) -> bytes::Bytes {
+        retained.take()
     }
 }
 
-impl<'a, 'b, T: Follow<'a>> Into<&'b [u8]> for &'b SizePrefixedFlatbufferRetained<'a, T> {
-    fn into(self) -> &'b [u8] {
-        &self.data[..]
+impl<T: RetainedRoot> TryFrom<Vec<u8>> for SizePrefixedFlatbufferRetained<T> {
+    type Error = RetainError;
+    fn try_from(data: Vec<u8>) -> Result<Self, RetainError> {
+        Self::try_new(data)
     }
 }
 
-impl<'a, T: Follow<'a>> AsRef<[u8]> for SizePrefixedFlatbufferRetained<'a, T> {
+impl<T> From<SizePrefixedFlatbufferRetained<T>> for Vec<u8> {
+    fn from(retained: SizePrefixedFlatbufferRetained<T>) -> Vec<u8> {
+        let (head, end) = (retained.head, retained.end);
+        let mut data = retained.take();
+        data.truncate(end);
+        data.drain(..head);
+        data
+    }
+}
+
+impl<'b, T, B: StableBytes> From<&'b SizePrefixedFlatbufferRetained<T, B>> for &'b [u8] {
+    fn from(retained: &'b SizePrefixedFlatbufferRetained<T, B>) -> &'b [u8] {
+        retained.bytes()
+    }
+}
+
+impl<T, B: StableBytes> AsRef<[u8]> for SizePrefixedFlatbufferRetained<T, B> {
     fn as_ref(&self) -> &[u8] {
-        self.data.as_ref()
+        self.bytes()
     }
 }
 
-impl<'a, T: Follow<'a>> std::borrow::Borrow<[u8]> for SizePrefixedFlatbufferRetained<'a, T> {
+impl<T, B: StableBytes> core::borrow::Borrow<[u8]> for SizePrefixedFlatbufferRetained<T, B> {
     fn borrow(&self) -> &[u8] {
-        self.data.borrow()
+        self.bytes()
     }
 }
 
-impl<'a, T: Follow<'a>> std::ops::Deref for SizePrefixedFlatbufferRetained<'a, T> {
+impl<T, B: StableBytes> core::ops::Deref for SizePrefixedFlatbufferRetained<T, B> {
     type Target = [u8];
     fn deref(&self) -> &[u8] {
-        self.data.deref()
+        self.bytes()
     }
 }
 
-impl<'a, T: Follow<'a>> IntoIterator for SizePrefixedFlatbufferRetained<'a, T> {
+impl<T, B> IntoIterator for SizePrefixedFlatbufferRetained<T, B>
+where
+    B: StableBytes + IntoIterator<Item = u8>,
+{
     type Item = u8;
-    type IntoIter = <Vec<u8> as IntoIterator>::IntoIter;
-    fn into_iter(self) -> <Vec<u8> as IntoIterator>::IntoIter {
-        self.data.into_iter()
+    type IntoIter = core::iter::Take<core::iter::Skip<B::IntoIter>>;
+    fn into_iter(self) -> core::iter::Take<core::iter::Skip<B::IntoIter>> {
+        let len = self.end - self.head;
+        self.data.into_iter().skip(self.head).take(len)
     }
 }
@@ -0,0 +1,48 @@
+//! This module defines the storage that retained flatbuffers may be
+//! kept in.
+//!
+//! Retained values read their bytes without re-verifying them, so the
+//! storage must hand back the bytes that were verified on every call.
+//! `AsRef` and `Clone` are safe traits that promise no such thing: an
+//! implementation could return different bytes each time, or clone into
+//! different bytes, and a retained value would then read unverified data.
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Storage whose bytes cannot change while it is alive.
+///
+/// # Safety
+///
+/// `as_ref` must return the same bytes every time it is called on a
+/// value, for as long as the value lives, including after it is moved.
+/// If the type implements Clone, a clone must hold the same bytes as
+/// the value it was cloned from.
+pub unsafe trait StableBytes: AsRef<[u8]> {}
+
+unsafe impl StableBytes for Vec<u8> {}
+unsafe impl StableBytes for Box<[u8]> {}
+unsafe impl StableBytes for Arc<[u8]> {}
+unsafe impl StableBytes for &[u8] {}
+
+#[cfg(feature = "bytes")]
+unsafe impl StableBytes for bytes::Bytes {}
+
+#[cfg(feature = "allocator-api2")]
+unsafe impl<A: allocator_api2::alloc::Allocator> StableBytes for allocator_api2::vec::Vec<u8, A> {}
+
+#[cfg(feature = "mmap")]
+unsafe impl StableBytes for crate::mmap::MappedFile {}
+
+/// Storage that shares its bytes between clones instead of copying
+/// them, which projections into RetainedRef handles require.
+/// Retained values in `Vec<u8>` storage move into shared storage with
+/// `into_arc`.
+pub trait SharedBytes: StableBytes + Clone {}
+
+impl SharedBytes for Arc<[u8]> {}
+impl SharedBytes for &[u8] {}
+
+#[cfg(feature = "bytes")]
+impl SharedBytes for bytes::Bytes {}
@@ -0,0 +1,67 @@
+//! This module focuses on strict length checks, which make sure a
+//! buffer holds nothing but its flatbuffer.
+//!
+//! A size prefix settles this on its own. Without one the end of the
+//! flatbuffer is taken to be the end of what the verifier reads, which
+//! only covers the fields the compiled schema knows about and takes
+//! O(log n) verifier passes to find.
+
+use flatbuffers::{SIZE_SIZEPREFIX, SIZE_UOFFSET};
+
+use crate::Error;
+
+/// The most zero bytes of padding a FlatBufferBuilder can leave after
+/// the last object in a buffer, one less than the largest alignment
+/// flatc accepts for `force_align`.
+pub(crate) const MAX_TRAILING_PADDING: usize = 31;
+
+/// Return the size declared by the size prefix of `data`, or None if
+/// `data` is too short to hold one.
+pub(crate) fn declared_size(data: &[u8]) -> Option<usize> {
+    let prefix: [u8; SIZE_SIZEPREFIX] = data.get(..SIZE_SIZEPREFIX)?.try_into().ok()?;
+    Some(u32::from_le_bytes(prefix) as usize)
+}
+
+/// Check that the size prefix of `data` declares exactly the bytes after it.
+pub(crate) fn check_declared_size(data: &[u8]) -> Result<(), Error> {
+    let actual = data.len().saturating_sub(SIZE_SIZEPREFIX);
+    match declared_size(data) {
+        Some(declared) if declared == actual => Ok(()),
+        declared => Err(Error::SizePrefixMismatch {
+            declared: declared.unwrap_or(0),
+            actual,
+        }),
+    }
+}
+
+/// Return the length of the shortest prefix of `data` that `verifies`,
+/// which is the extent of the bytes the verifier reads. Verification is
+/// monotone in the length, since a longer buffer only brings more reads
+/// in range, so a binary search needs O(log n) verifier passes.
+/// `verifies(data)` must hold.
+pub(crate) fn verified_extent(data: &[u8], verifies: impl Fn(&[u8]) -> bool) -> usize {
+    // No flatbuffer is shorter than its root offset.
+    let (mut lo, mut hi) = (SIZE_UOFFSET.min(data.len()), data.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if verifies(&data[..mid]) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    hi
+}
+
+/// Check that nothing but builder padding follows `extent` in `data`.
+pub(crate) fn check_trailing_bytes(data: &[u8], extent: usize) -> Result<(), Error> {
+    let trailing = &data[extent..];
+    if trailing.len() <= MAX_TRAILING_PADDING && trailing.iter().all(|&b| b == 0) {
+        Ok(())
+    } else {
+        Err(Error::TrailingBytes {
+            extent,
+            len: data.len(),
+        })
+    }
+}
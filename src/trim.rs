@@ -0,0 +1,28 @@
+//! This module focuses on shrinking the storage of retained buffers
+//! down to the flatbuffer they hold.
+//!
+//! Flatbuffers carry no schema, so which bytes inside a flatbuffer are
+//! reachable cannot be worked out generically. Bytes that T's schema
+//! does not reach may still be read by a newer schema, through fields
+//! T does not know about. Trimming therefore only drops the bytes
+//! around the flatbuffer: builder slack, custom headers and trailers.
+//! Unreachable regions inside a flatbuffer are removed by `rebuild`,
+//! which copies the root into a fresh FlatBufferBuilder with schema
+//! aware code such as the generated object API.
+
+/// How much memory trimming a retained value saves, as reported by
+/// `trim_report` without copying anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrimReport {
+    /// The length of the storage before trimming.
+    pub before: usize,
+    /// The length of the storage after trimming.
+    pub after: usize,
+}
+
+impl TrimReport {
+    /// Return how many bytes trimming saves.
+    pub fn saved(&self) -> usize {
+        self.before - self.after
+    }
+}
@@ -0,0 +1,208 @@
+//! This module focuses on checking invariants the schema cannot
+//! express, such as a field being in range, once when a buffer is
+//! retained, and recording in the type that they were checked.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops::Deref;
+use flatbuffers::{InvalidFlatbuffer, VerifierOptions};
+
+use crate::{RetainError, Retained, RetainedRoot, StableBytes};
+
+/// A policy of invariants a verified root of type T must satisfy.
+/// Policies are usually unit structs that exist only to name the
+/// checks in the type `Validated<T, Policy>`.
+pub trait Validator<T: RetainedRoot> {
+    /// Why a root breaks the policy.
+    type Error;
+
+    /// Check the invariants of the policy on a structurally verified root.
+    fn validate(root: &T::Table<'_>) -> Result<(), Self::Error>;
+}
+
+/// An error returned when a buffer fails structural verification or
+/// breaks the policy it is validated against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError<E> {
+    /// The data is not a valid flatbuffer of the requested type.
+    InvalidFlatbuffer(InvalidFlatbuffer),
+    /// The flatbuffer is valid but breaks the policy.
+    Policy(E),
+}
+
+impl<E> From<InvalidFlatbuffer> for ValidationError<E> {
+    fn from(err: InvalidFlatbuffer) -> Self {
+        ValidationError::InvalidFlatbuffer(err)
+    }
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for ValidationError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ValidationError::InvalidFlatbuffer(err) => err.fmt(f),
+            ValidationError::Policy(err) => write!(f, "Validation failed: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for ValidationError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ValidationError::InvalidFlatbuffer(err) => Some(err),
+            ValidationError::Policy(err) => Some(err),
+        }
+    }
+}
+
+/// This struct holds a flatbuffer of type T that passed both the
+/// flatbuffers verifier and the policy P. Functions taking a
+/// `Validated<T, P>` can rely on the invariants of P without checking
+/// them again. It dereferences to the underlying Retained.
+pub struct Validated<T, P, B = Vec<u8>> {
+    /// The verified flatbuffer.
+    retained: Retained<T, B>,
+    /// Phantom data to place-hold which policy the data was validated for.
+    policy: PhantomData<fn() -> P>,
+}
+
+impl<T, P, B> Validated<T, P, B>
+where
+    T: RetainedRoot,
+    P: Validator<T>,
+    B: StableBytes,
+{
+    /// Make a new Validated class of type T from an unprefixed byte
+    /// buffer that must satisfy the policy P.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T.
+    ///   not prefixed by size.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data, or the error of P if the root breaks it.
+    pub fn new_unprefixed(data: B) -> Result<Self, ValidationError<P::Error>> {
+        Self::new_unprefixed_with_options(data, &VerifierOptions::default())
+    }
+
+    /// Make a new Validated class of type T from a size-prefixed byte
+    /// buffer that must satisfy the policy P.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data, or the error of P if the root breaks it.
+    pub fn new_size_prefixed(data: B) -> Result<Self, ValidationError<P::Error>> {
+        Self::new_size_prefixed_with_options(data, &VerifierOptions::default())
+    }
+
+    /// Make a new Validated class of type T from an unprefixed byte
+    /// buffer that must satisfy the policy P, verifying it with custom
+    /// options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T.
+    ///   not prefixed by size.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data, or the error of P if the root breaks it.
+    pub fn new_unprefixed_with_options(
+        data: B,
+        opts: &VerifierOptions,
+    ) -> Result<Self, ValidationError<P::Error>> {
+        let retained = Retained::new_unprefixed_with_options(data, opts)?;
+        Self::from_retained(retained).map_err(|err| ValidationError::Policy(err.into_parts().0))
+    }
+
+    /// Make a new Validated class of type T from a size-prefixed byte
+    /// buffer that must satisfy the policy P, verifying it with custom
+    /// options.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data that represents a flatbuffer of type T,
+    ///   prefixed by size.
+    /// * `opts` - The limits the verifier enforces while parsing the data.
+    ///
+    /// # Errors
+    ///
+    /// May return any InvalidFlatbuffer error from run_verifier
+    /// when it parses the data, or the error of P if the root breaks it.
+    pub fn new_size_prefixed_with_options(
+        data: B,
+        opts: &VerifierOptions,
+    ) -> Result<Self, ValidationError<P::Error>> {
+        let retained = Retained::new_size_prefixed_with_options(data, opts)?;
+        Self::from_retained(retained).map_err(|err| ValidationError::Policy(err.into_parts().0))
+    }
+
+    /// Check the policy P on an already verified flatbuffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `retained` - The verified flatbuffer, which may be a
+    ///   FlatbufferRetained or SizePrefixedFlatbufferRetained
+    ///   converted with `into()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a RetainError holding the error of P together with
+    /// the rejected flatbuffer.
+    pub fn from_retained(
+        retained: Retained<T, B>,
+    ) -> Result<Self, RetainError<Retained<T, B>, P::Error>> {
+        let checked = P::validate(&retained.get());
+        match checked {
+            Ok(()) => Ok(Validated {
+                retained,
+                policy: PhantomData,
+            }),
+            Err(err) => Err(RetainError::new(err, retained)),
+        }
+    }
+}
+
+impl<T, P, B> Validated<T, P, B> {
+    /// Deconstruct this class and return the verified flatbuffer,
+    /// forgetting that it satisfies P.
+    pub fn into_retained(self) -> Retained<T, B> {
+        self.retained
+    }
+}
+
+impl<T, P, B> Deref for Validated<T, P, B> {
+    type Target = Retained<T, B>;
+    fn deref(&self) -> &Retained<T, B> {
+        &self.retained
+    }
+}
+
+impl<T: Clone, P, B: Clone> Clone for Validated<T, P, B> {
+    fn clone(&self) -> Self {
+        Validated {
+            retained: self.retained.clone(),
+            policy: PhantomData,
+        }
+    }
+}
+
+impl<T: core::fmt::Debug, P, B: core::fmt::Debug> core::fmt::Debug for Validated<T, P, B> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Validated")
+            .field("retained", &self.retained)
+            .field("policy", &core::any::type_name::<P>())
+            .finish()
+    }
+}
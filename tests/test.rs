@@ -3,23 +3,26 @@ extern crate flatbuffers_retained;
 
 use flatbuffers_retained::FlatbufferRetained;
 
+#[allow(clippy::all, mismatched_lifetime_syntaxes)]
 mod monster_generated;
 
 pub use monster_generated::my_game::sample::{
     root_as_monster, Color, Equipment, Monster, MonsterArgs, Vec3, Weapon, WeaponArgs,
 };
 
-fn do_some_checks<'a>(monster: &SerializedMonster<'a>) {
+flatbuffers_retained::retained_root!(Monster, Weapon);
+
+fn do_some_checks(monster: &SerializedMonster) {
     assert_eq!(monster.get_hp(), 80);
     assert_eq!(monster.get().mana(), 150);
     assert_eq!(monster.get().pos().unwrap().y(), 2.0f32);
 }
 
 #[derive(Default)]
-struct MonstersHolder<'a> {
-    monsters: std::collections::HashMap<i16, SerializedMonster<'a>>,
+struct MonstersHolder {
+    monsters: std::collections::HashMap<i16, SerializedMonster>,
 }
-impl<'a> MonstersHolder<'a> {
+impl MonstersHolder {
     fn do_some_checks(&self) {
         for v in self.monsters.values() {
             do_some_checks(v);
@@ -27,21 +30,9 @@ impl<'a> MonstersHolder<'a> {
     }
 }
 
-struct SerializedMonster<'a> {
-    monster: flatbuffers_retained::FlatbufferRetained<'a, Monster<'a>>,
-}
-
-impl<'a> SerializedMonster<'a> {
-    pub fn new(data: Vec<u8>) -> Result<Self, flatbuffers::InvalidFlatbuffer> {
-        Ok(SerializedMonster {
-            monster: FlatbufferRetained::new(data)?,
-        })
-    }
-    pub fn get_hp(&self) -> i16 {
-        self.monster.get().hp()
-    }
-    pub fn get(&'a self) -> <Monster<'a> as flatbuffers::Follow<'a>>::Inner {
-        self.monster.get()
+flatbuffers_retained::retained_type! {
+    struct SerializedMonster(Monster) {
+        fn get_hp() -> i16 => hp;
     }
 }
 
@@ -180,3 +171,1616 @@ fn test_retained() {
     let slice: &[u8] = (&monster2).into();
     assert_eq!(slice, builder.finished_data());
 }
+
+#[test]
+fn test_verifier_options() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            pos: Some(&Vec3::new(1.0f32, 2.0f32, 3.0f32)),
+            mana: 150,
+            hp: 80,
+            ..Default::default()
+        },
+    );
+    builder.finish(orc, None);
+    let monster = FlatbufferRetained::<Monster>::new_with_options(
+        builder.finished_data().to_vec(),
+        &flatbuffers_retained::options::UNTRUSTED_NETWORK,
+    )
+    .unwrap();
+    assert_eq!(monster.get().hp(), 80);
+
+    let no_tables = flatbuffers::VerifierOptions {
+        max_tables: 0,
+        ..Default::default()
+    };
+    assert_eq!(
+        FlatbufferRetained::<Monster>::new_with_options(
+            builder.finished_data().to_vec(),
+            &no_tables
+        )
+        .map(|_| false)
+        .unwrap_err(),
+        flatbuffers::InvalidFlatbuffer::TooManyTables
+    );
+    flatbuffers_retained::Retained::<Monster>::new_unprefixed_with_options(
+        builder.finished_data().to_vec(),
+        &no_tables,
+    )
+    .map(|_| false)
+    .unwrap_err();
+
+    builder.reset();
+    let orc2 = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 81,
+            ..Default::default()
+        },
+    );
+    builder.finish_size_prefixed(orc2, None);
+    let monster2 = flatbuffers_retained::Retained::<Monster>::new_size_prefixed_with_options(
+        builder.finished_data().to_vec(),
+        &flatbuffers_retained::options::TRUSTED_LARGE_FILE,
+    )
+    .unwrap();
+    assert_eq!(monster2.get().hp(), 81);
+    flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::new_with_options(
+        builder.finished_data().to_vec(),
+        &no_tables,
+    )
+    .map(|_| false)
+    .unwrap_err();
+}
+
+#[test]
+fn test_file_identifier() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 80,
+            ..Default::default()
+        },
+    );
+    builder.finish(orc, Some("MONS"));
+    let monster = FlatbufferRetained::<Monster>::new_with_identifier(
+        builder.finished_data().to_vec(),
+        "MONS",
+    )
+    .unwrap();
+    assert_eq!(monster.get().hp(), 80);
+    assert_eq!(
+        FlatbufferRetained::<Monster>::new_with_identifier(
+            builder.finished_data().to_vec(),
+            "WEAP"
+        )
+        .map(|_| false)
+        .unwrap_err(),
+        flatbuffers_retained::Error::IdentifierMismatch {
+            expected: *b"WEAP",
+            found: Some(*b"MONS"),
+        }
+    );
+    // The identifier sits behind the size prefix in a size-prefixed buffer.
+    flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::new_with_identifier(
+        builder.finished_data().to_vec(),
+        "MONS",
+    )
+    .map(|_| false)
+    .unwrap_err();
+    assert_eq!(
+        FlatbufferRetained::<Monster>::new_with_identifier(vec![0u8; 6], "MONS")
+            .map(|_| false)
+            .unwrap_err(),
+        flatbuffers_retained::Error::IdentifierMismatch {
+            expected: *b"MONS",
+            found: None,
+        }
+    );
+
+    builder.reset();
+    let orc2 = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 81,
+            ..Default::default()
+        },
+    );
+    builder.finish_size_prefixed(orc2, Some("MONS"));
+    let monster2 = flatbuffers_retained::Retained::<Monster>::new_size_prefixed_with_identifier(
+        builder.finished_data().to_vec(),
+        "MONS",
+    )
+    .unwrap();
+    assert_eq!(monster2.get().hp(), 81);
+    flatbuffers_retained::Retained::<Monster>::new_unprefixed_with_identifier(
+        builder.finished_data().to_vec(),
+        "MONS",
+    )
+    .map(|_| false)
+    .unwrap_err();
+}
+
+#[test]
+fn test_auto_detect() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 80,
+            ..Default::default()
+        },
+    );
+    builder.finish(orc, None);
+    let monster =
+        flatbuffers_retained::Retained::<Monster>::new_auto(builder.finished_data().to_vec())
+            .unwrap();
+    assert!(matches!(
+        monster,
+        flatbuffers_retained::Retained::Unprefixed(_)
+    ));
+    assert_eq!(monster.get().hp(), 80);
+
+    builder.reset();
+    let orc2 = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 81,
+            ..Default::default()
+        },
+    );
+    builder.finish_size_prefixed(orc2, None);
+    let monster2 =
+        flatbuffers_retained::Retained::<Monster>::new_auto(builder.finished_data().to_vec())
+            .unwrap();
+    assert!(matches!(
+        monster2,
+        flatbuffers_retained::Retained::SizePrefixed(_)
+    ));
+    assert_eq!(monster2.get().hp(), 81);
+
+    let err = flatbuffers_retained::Retained::<Monster>::new_auto(vec![0xff; 16])
+        .map(|_| false)
+        .unwrap_err();
+    assert_eq!(
+        *err.unprefixed,
+        flatbuffers_retained::FlatbufferRetained::<Monster>::new(vec![0xff; 16])
+            .map(|_| false)
+            .unwrap_err()
+    );
+    assert_eq!(
+        *err.size_prefixed,
+        flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::new(vec![0xff; 16])
+            .map(|_| false)
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_storage() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 80,
+            ..Default::default()
+        },
+    );
+    builder.finish(orc, None);
+
+    let boxed: Box<[u8]> = builder.finished_data().into();
+    let monster = FlatbufferRetained::<Monster, Box<[u8]>>::new(boxed).unwrap();
+    assert_eq!(monster.get().hp(), 80);
+    assert_eq!(monster.as_ref(), builder.finished_data());
+
+    let shared: std::sync::Arc<[u8]> = builder.finished_data().into();
+    let monster = FlatbufferRetained::<Monster, std::sync::Arc<[u8]>>::new(shared.clone()).unwrap();
+    assert_eq!(monster.get().hp(), 80);
+    assert!(std::sync::Arc::ptr_eq(monster.storage(), &shared));
+
+    let borrowed =
+        flatbuffers_retained::Retained::<Monster, &[u8]>::new_unprefixed(builder.finished_data())
+            .unwrap();
+    assert_eq!(borrowed.get().hp(), 80);
+    assert_eq!(borrowed.take(), builder.finished_data());
+
+    #[cfg(feature = "bytes")]
+    {
+        let monster = FlatbufferRetained::<Monster>::new(builder.finished_data().to_vec()).unwrap();
+        let monster = monster.into_bytes();
+        assert_eq!(monster.get().hp(), 80);
+        let bytes: bytes::Bytes = monster.into();
+        assert_eq!(&bytes[..], builder.finished_data());
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    {
+        let mut data = allocator_api2::vec::Vec::new_in(allocator_api2::alloc::Global);
+        data.extend_from_slice(builder.finished_data());
+        let monster = flatbuffers_retained::SizePrefixedFlatbufferRetained::<
+            Monster,
+            allocator_api2::vec::Vec<u8, allocator_api2::alloc::Global>,
+        >::new(data);
+        monster.map(|_| false).unwrap_err();
+        let mut data = allocator_api2::vec::Vec::new_in(allocator_api2::alloc::Global);
+        data.extend_from_slice(builder.finished_data());
+        let monster = FlatbufferRetained::<Monster, _>::new(data).unwrap();
+        assert_eq!(&monster.storage()[..], builder.finished_data());
+    }
+}
+
+#[test]
+fn test_without_lifetimes() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 80,
+            ..Default::default()
+        },
+    );
+    builder.finish(orc, None);
+    let monster =
+        FlatbufferRetained::<Monster<'static>>::new(builder.finished_data().to_vec()).unwrap();
+
+    // The retained value is 'static, so it can be type-erased.
+    let erased: Box<dyn std::any::Any> = Box::new(monster);
+    let monster = erased
+        .downcast_ref::<FlatbufferRetained<Monster<'static>>>()
+        .unwrap();
+    assert_eq!(monster.get().hp(), 80);
+
+    // Values held behind a &mut container stay usable.
+    let mut holder = MonstersHolder::default();
+    holder.monsters.insert(
+        1,
+        SerializedMonster::new(builder.finished_data().to_vec()).unwrap(),
+    );
+    let monsters = &mut holder.monsters;
+    let hp = monsters.get(&1).unwrap().get_hp();
+    *monsters.get_mut(&1).unwrap() = FlatbufferRetained::new(builder.finished_data().to_vec())
+        .unwrap()
+        .into();
+    assert_eq!(monsters.get(&1).unwrap().get().hp(), hp);
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mmap() {
+    use flatbuffers_retained::mmap::{MappedFile, MmapRetained, SizePrefixedMmapRetained};
+
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 80,
+            ..Default::default()
+        },
+    );
+    builder.finish(orc, None);
+    let path = std::env::temp_dir().join(format!(
+        "flatbuffers-retained-test-mmap-{}.bin",
+        std::process::id()
+    ));
+    std::fs::write(&path, builder.finished_data()).unwrap();
+
+    // Safety: nothing else writes to this file while it is mapped.
+    let monster =
+        MmapRetained::<Monster>::new(unsafe { MappedFile::open(&path) }.unwrap()).unwrap();
+    assert_eq!(monster.get().hp(), 80);
+    assert_eq!(monster.as_ref(), builder.finished_data());
+
+    // Cooperating writers cannot lock the file while it is mapped.
+    let writer = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+    assert!(!fs4::fs_std::FileExt::try_lock_exclusive(&writer).unwrap());
+    drop(writer);
+
+    SizePrefixedMmapRetained::<Monster>::new(unsafe { MappedFile::open(&path) }.unwrap())
+        .map(|_| false)
+        .unwrap_err();
+    drop(monster);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_from_builder() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 80,
+            ..Default::default()
+        },
+    );
+    builder.finish(orc, None);
+    let expected = builder.finished_data().to_vec();
+    let monster = FlatbufferRetained::<Monster>::from_builder(builder).unwrap();
+    assert_eq!(monster.get().hp(), 80);
+    assert_ne!(monster.head(), 0);
+    assert_eq!(&monster[..], &expected[..]);
+    assert_eq!(monster.iter().count(), expected.len());
+    assert_eq!(monster.clone().into_iter().collect::<Vec<u8>>(), expected);
+    let data: Vec<u8> = monster.into();
+    assert_eq!(data, expected);
+
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc2 = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 81,
+            ..Default::default()
+        },
+    );
+    builder.finish_size_prefixed(orc2, None);
+    // Safety: the builder was finished size-prefixed with a Monster root.
+    let monster2 = unsafe {
+        flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::from_builder_unchecked(
+            builder,
+        )
+    };
+    assert_eq!(monster2.get().hp(), 81);
+
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc3 = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 82,
+            ..Default::default()
+        },
+    );
+    builder.finish_size_prefixed(orc3, None);
+    FlatbufferRetained::<Monster>::from_builder(builder)
+        .map(|_| false)
+        .unwrap_err();
+}
+
+#[test]
+fn test_embedded_at_offset() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 80,
+            ..Default::default()
+        },
+    );
+    builder.finish(orc, None);
+    let header = [7u8; 16];
+    let mut data = header.to_vec();
+    data.extend_from_slice(builder.finished_data());
+    data.extend_from_slice(b"tail");
+
+    let len = builder.finished_data().len();
+    let monster = FlatbufferRetained::<Monster>::new_at(data.clone(), 16, Some(len)).unwrap();
+    assert_eq!(monster.get().hp(), 80);
+    assert_eq!(monster.header(), &header[..]);
+    assert_eq!(monster.trailer(), b"tail");
+    assert_eq!(&monster[..], builder.finished_data());
+    let slice: &[u8] = (&monster).into();
+    assert_eq!(slice, builder.finished_data());
+    assert_eq!(
+        monster.clone().into_iter().collect::<Vec<u8>>(),
+        builder.finished_data()
+    );
+    assert_eq!(monster.take(), data);
+
+    // The region must lie within the data.
+    FlatbufferRetained::<Monster>::new_at(data.clone(), 16, Some(data.len()))
+        .map(|_| false)
+        .unwrap_err();
+    FlatbufferRetained::<Monster>::new_at(data.clone(), data.len() + 1, None)
+        .map(|_| false)
+        .unwrap_err();
+
+    builder.reset();
+    let orc2 = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 81,
+            ..Default::default()
+        },
+    );
+    builder.finish_size_prefixed(orc2, None);
+    let mut data = header.to_vec();
+    data.extend_from_slice(builder.finished_data());
+    let monster2 =
+        flatbuffers_retained::Retained::<Monster>::new_size_prefixed_at(data, 16, None).unwrap();
+    assert_eq!(monster2.get().hp(), 81);
+    assert_eq!(monster2.header(), &header[..]);
+    let data: Vec<u8> = monster2.into();
+    assert_eq!(data, builder.finished_data());
+}
+
+fn build_armed_monster(builder: &mut flatbuffers::FlatBufferBuilder) {
+    let sword_name = builder.create_string("Sword");
+    let sword = Weapon::create(
+        builder,
+        &WeaponArgs {
+            name: Some(sword_name),
+            damage: 3,
+        },
+    );
+    let axe_name = builder.create_string("Axe");
+    let axe = Weapon::create(
+        builder,
+        &WeaponArgs {
+            name: Some(axe_name),
+            damage: 5,
+        },
+    );
+    let weapons = builder.create_vector(&[sword, axe]);
+    let orc = Monster::create(
+        builder,
+        &MonsterArgs {
+            hp: 80,
+            weapons: Some(weapons),
+            equipped_type: Equipment::Weapon,
+            equipped: Some(axe.as_union_value()),
+            ..Default::default()
+        },
+    );
+    builder.finish(orc, None);
+}
+
+#[test]
+fn test_project_sub_tables() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    build_armed_monster(&mut builder);
+    let monster = FlatbufferRetained::<Monster>::new(builder.finished_data().to_vec())
+        .unwrap()
+        .into_arc();
+
+    let sword = monster
+        .project::<Weapon, _>(|m| m.get().weapons().map(|w| w.get(0)))
+        .unwrap();
+    let equipped = monster
+        .project::<Weapon, _>(|m| m.get().equipped_as_weapon())
+        .unwrap();
+    assert!(monster
+        .project::<Weapon, _>(|m| m
+            .get()
+            .weapons()
+            .and_then(|w| w.iter().find(|w| w.damage() > 10)))
+        .is_none());
+    drop(monster);
+
+    // The handles outlive the parent and share its storage.
+    let mut armory: std::collections::HashMap<&str, flatbuffers_retained::RetainedRef<Weapon>> =
+        std::collections::HashMap::new();
+    armory.insert("sword", sword);
+    armory.insert("equipped", equipped.clone());
+    assert_eq!(armory["sword"].get().name(), Some("Sword"));
+    assert_eq!(armory["sword"].get().damage(), 3);
+    assert_eq!(armory["equipped"].get().name(), Some("Axe"));
+    assert!(std::sync::Arc::ptr_eq(
+        armory["sword"].storage(),
+        equipped.storage()
+    ));
+
+    let retained =
+        flatbuffers_retained::Retained::<Monster>::new_unprefixed(builder.finished_data().to_vec())
+            .unwrap()
+            .into_arc();
+    let axe = retained
+        .project::<Weapon, _>(|m| m.get().weapons().map(|w| w.get(1)))
+        .unwrap();
+    assert_eq!(axe.get().damage(), 5);
+}
+
+#[test]
+#[should_panic(expected = "does not belong")]
+fn test_project_foreign_table() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    build_armed_monster(&mut builder);
+    let monster = FlatbufferRetained::<Monster>::new(builder.finished_data().to_vec())
+        .unwrap()
+        .into_arc();
+    let other = FlatbufferRetained::<Monster>::new(builder.finished_data().to_vec()).unwrap();
+    let other_root = other.get();
+    monster.project::<Monster, _>(|_| Some(other_root));
+}
+
+/// A hand-written table holding another of its kind, so tables can be
+/// nested deeper than the generated schema allows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Link<'a> {
+    _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> Link<'a> {
+    const VT_NEXT: flatbuffers::VOffsetT = 4;
+
+    fn next(&self) -> Option<Link<'a>> {
+        // Safety: the field was verified as a Link.
+        unsafe {
+            self._tab
+                .get::<flatbuffers::ForwardsUOffset<Link>>(Self::VT_NEXT, None)
+        }
+    }
+}
+
+impl<'a> flatbuffers::Follow<'a> for Link<'a> {
+    type Inner = Link<'a>;
+
+    unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table::new(buf, loc),
+        }
+    }
+}
+
+impl flatbuffers::Verifiable for Link<'_> {
+    fn run_verifier(
+        v: &mut flatbuffers::Verifier,
+        pos: usize,
+    ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+        v.visit_table(pos)?
+            .visit_field::<flatbuffers::ForwardsUOffset<Link>>("next", Self::VT_NEXT, false)?
+            .finish();
+        Ok(())
+    }
+}
+
+flatbuffers_retained::retained_root!(Link);
+
+#[test]
+fn test_project_deeply_nested() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let mut next = None;
+    for _ in 0..100 {
+        let start = builder.start_table();
+        if let Some(next) = next {
+            builder.push_slot_always::<flatbuffers::WIPOffset<Link>>(Link::VT_NEXT, next);
+        }
+        next = Some(flatbuffers::WIPOffset::new(
+            builder.end_table(start).value(),
+        ));
+    }
+    builder.finish(next.unwrap(), None);
+    let data = builder.finished_data().to_vec();
+    assert!(FlatbufferRetained::<Link>::new_with_options(
+        data.clone(),
+        &flatbuffers_retained::options::TRUSTED_LARGE_FILE
+    )
+    .is_err());
+
+    // Projections stay within the limits the whole buffer passed.
+    let opts = flatbuffers::VerifierOptions {
+        max_depth: 128,
+        ..Default::default()
+    };
+    let chain = FlatbufferRetained::<Link>::new_with_options(data, &opts)
+        .unwrap()
+        .into_arc();
+    let second = chain.project::<Link, _>(|c| c.get().next()).unwrap();
+    let third = second.project::<Link, _>(|l| l.get().next()).unwrap();
+    assert_eq!(third.get(), chain.get().next().unwrap().next().unwrap());
+    let mut depth = 1;
+    let mut link = third.get();
+    while let Some(next) = link.next() {
+        link = next;
+        depth += 1;
+    }
+    assert_eq!(depth, 98);
+}
+
+#[test]
+fn test_rejected_data_is_returned() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 81,
+            ..Default::default()
+        },
+    );
+    builder.finish_size_prefixed(orc, None);
+    let data = builder.finished_data().to_vec();
+
+    // Retry a rejected unprefixed buffer as size-prefixed without copying it.
+    let err = FlatbufferRetained::<Monster>::try_from(data.clone()).unwrap_err();
+    let expected = FlatbufferRetained::<Monster>::new(data.clone())
+        .map(|_| false)
+        .unwrap_err();
+    assert_eq!(err.error(), &expected);
+    assert_eq!(err.data(), &data);
+    let monster2 =
+        flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::try_from(err.into_inner())
+            .unwrap();
+    assert_eq!(monster2.get().hp(), 81);
+
+    let err = flatbuffers_retained::Retained::<Monster>::try_new_unprefixed(data.clone())
+        .map(|_| false)
+        .unwrap_err();
+    let invalid: flatbuffers::InvalidFlatbuffer = err.into();
+    assert_eq!(invalid, expected);
+    flatbuffers_retained::Retained::<Monster>::try_new_size_prefixed(data.clone()).unwrap();
+
+    let retained = flatbuffers_retained::Retained::<Monster>::try_from(data).unwrap();
+    assert_eq!(retained.get().hp(), 81);
+    let err = flatbuffers_retained::Retained::<Monster>::try_from(vec![0xff; 16])
+        .map(|_| false)
+        .unwrap_err();
+    assert_eq!(err.data(), &vec![0xff; 16]);
+    assert!(format!("{}", err).contains("size-prefixed"));
+}
+
+#[test]
+fn test_shared_across_threads() {
+    fn assert_send_sync<S: Send + Sync + 'static>(_: &S) {}
+
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    build_armed_monster(&mut builder);
+    let monster: flatbuffers_retained::SharedRetained<Monster<'static>> =
+        FlatbufferRetained::<Monster>::new(builder.finished_data().to_vec())
+            .unwrap()
+            .into();
+    assert_send_sync(&monster);
+    assert!(!monster.is_size_prefixed());
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            let monster = monster.clone();
+            scope.spawn(move || {
+                assert_eq!(monster.get().hp(), 80);
+                assert_eq!(monster.get().weapons().unwrap().len(), 2);
+            });
+        }
+        scope.spawn(|| assert_eq!(monster.get().equipped_as_weapon().unwrap().damage(), 5));
+    });
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let worker = std::thread::spawn(move || {
+        receiver
+            .iter()
+            .map(
+                |monster: flatbuffers_retained::SharedRetained<Monster<'static>>| {
+                    monster.get().hp()
+                },
+            )
+            .sum::<i16>()
+    });
+    for _ in 0..3 {
+        sender.send(monster.clone()).unwrap();
+    }
+    drop(sender);
+    assert_eq!(worker.join().unwrap(), 240);
+    assert_eq!(std::sync::Arc::strong_count(monster.storage()), 1);
+
+    // Arc-backed values convert without copying.
+    let arc_backed = FlatbufferRetained::<Monster>::new(builder.finished_data().to_vec())
+        .unwrap()
+        .into_arc();
+    let storage = arc_backed.storage().clone();
+    let shared: flatbuffers_retained::SharedRetained<Monster> = arc_backed.into();
+    assert!(std::sync::Arc::ptr_eq(shared.storage(), &storage));
+    let sword = shared
+        .project::<Weapon, _>(|m| m.get().weapons().map(|w| w.get(0)))
+        .unwrap();
+    assert_eq!(sword.get().name(), Some("Sword"));
+
+    builder.reset();
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 81,
+            ..Default::default()
+        },
+    );
+    builder.finish_size_prefixed(orc, None);
+    let monster2: flatbuffers_retained::SharedRetained<Monster> =
+        flatbuffers_retained::Retained::<Monster>::new_size_prefixed(
+            builder.finished_data().to_vec(),
+        )
+        .unwrap()
+        .into();
+    assert!(monster2.is_size_prefixed());
+    std::thread::scope(|scope| {
+        scope.spawn(|| assert_eq!(monster2.get().hp(), 81));
+    });
+    assert_eq!(&monster2[..], builder.finished_data());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_lazy_verification() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 80,
+            ..Default::default()
+        },
+    );
+    builder.finish(orc, None);
+
+    let lazy = flatbuffers_retained::LazyRetained::<Monster>::new_unprefixed(
+        builder.finished_data().to_vec(),
+    );
+    assert!(!lazy.is_verified());
+    assert_eq!(lazy.try_get().unwrap().hp(), 80);
+    assert!(lazy.is_verified());
+    assert_eq!(lazy.try_get().unwrap().hp(), 80);
+    // Clones hold the same bytes, so they keep the outcome.
+    let copy = lazy.clone();
+    assert!(copy.is_verified());
+    assert_eq!(copy.try_get().unwrap().hp(), 80);
+    match lazy.into_retained().unwrap() {
+        flatbuffers_retained::Retained::Unprefixed(monster) => assert_eq!(monster.get().hp(), 80),
+        flatbuffers_retained::Retained::SizePrefixed(_) => panic!("framing changed"),
+    }
+
+    // Converting verifies data that was never accessed.
+    let lazy = flatbuffers_retained::LazyRetained::<Monster>::new_size_prefixed(
+        builder.finished_data().to_vec(),
+    );
+    let err = lazy.clone().try_get().map(|_| false).unwrap_err();
+    assert_eq!(
+        err,
+        flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::new(
+            builder.finished_data().to_vec()
+        )
+        .map(|_| false)
+        .unwrap_err()
+    );
+    let rejected = lazy.into_retained().map(|_| false).unwrap_err();
+    assert_eq!(rejected.error(), &err);
+    assert_eq!(rejected.into_inner(), builder.finished_data());
+
+    let no_tables = flatbuffers::VerifierOptions {
+        max_tables: 0,
+        ..Default::default()
+    };
+    let lazy = flatbuffers_retained::LazyRetained::<Monster, &[u8]>::new_unprefixed_with_options(
+        builder.finished_data(),
+        no_tables,
+    );
+    assert_eq!(
+        lazy.try_get().map(|_| false).unwrap_err(),
+        flatbuffers::InvalidFlatbuffer::TooManyTables
+    );
+    assert!(!lazy.is_verified());
+}
+
+#[test]
+fn test_batch_verification() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    build_armed_monster(&mut builder);
+    let good = builder.finished_data().to_vec();
+    let bad = good[..good.len() / 2].to_vec();
+    let buffers = vec![good.clone(), bad.clone(), good.clone(), bad.clone()];
+
+    let report = FlatbufferRetained::<Monster>::new_batch(
+        buffers.clone(),
+        flatbuffers_retained::BatchMode::CollectAll,
+    );
+    assert!(!report.is_ok());
+    assert_eq!(
+        report
+            .retained
+            .iter()
+            .map(|(index, _)| *index)
+            .collect::<Vec<_>>(),
+        vec![0, 2]
+    );
+    for (_, monster) in report.retained.iter() {
+        assert_eq!(monster.get().hp(), 80);
+    }
+    assert_eq!(
+        report
+            .errors
+            .iter()
+            .map(|(index, _)| *index)
+            .collect::<Vec<_>>(),
+        vec![1, 3]
+    );
+    assert_eq!(report.errors[0].1.data(), &bad);
+    assert!(report.skipped.is_empty());
+
+    let report = FlatbufferRetained::<Monster>::new_batch(
+        buffers,
+        flatbuffers_retained::BatchMode::FailFast,
+    );
+    assert!(!report.errors.is_empty());
+    assert_eq!(
+        report.retained.len() + report.errors.len() + report.skipped.len(),
+        4
+    );
+    assert!(report.errors.iter().all(|(index, _)| index % 2 == 1));
+
+    let report = flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::new_batch(
+        Vec::<Vec<u8>>::new(),
+        flatbuffers_retained::BatchMode::FailFast,
+    );
+    assert!(report.is_ok());
+    assert!(report.retained.is_empty());
+}
+
+#[cfg(feature = "cache")]
+#[test]
+fn test_verification_cache() {
+    let cache = flatbuffers_retained::VerificationCache::new(2);
+    let opts = flatbuffers::VerifierOptions::default();
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    build_armed_monster(&mut builder);
+    let data = builder.finished_data().to_vec();
+
+    let monster = FlatbufferRetained::<Monster>::new_cached(data.clone(), &opts, &cache).unwrap();
+    assert_eq!(monster.get().hp(), 80);
+    assert_eq!((cache.hits(), cache.misses()), (0, 1));
+    FlatbufferRetained::<Monster>::new_cached(data.clone(), &opts, &cache).unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+    // Another root type, framing or option set never reuses the entry.
+    let _ = FlatbufferRetained::<Weapon>::new_cached(data.clone(), &opts, &cache);
+    assert_eq!(cache.misses(), 2);
+    let _ = flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::new_cached(
+        data.clone(),
+        &opts,
+        &cache,
+    );
+    assert_eq!(cache.misses(), 3);
+    let no_tables = flatbuffers::VerifierOptions {
+        max_tables: 0,
+        ..Default::default()
+    };
+    assert_eq!(
+        FlatbufferRetained::<Monster>::new_cached(data.clone(), &no_tables, &cache)
+            .map(|_| false)
+            .unwrap_err(),
+        flatbuffers::InvalidFlatbuffer::TooManyTables
+    );
+    assert_eq!(cache.misses(), 4);
+
+    // Failures are never remembered.
+    let truncated = data[..data.len() / 2].to_vec();
+    for _ in 0..2 {
+        assert!(
+            FlatbufferRetained::<Monster>::new_cached(truncated.clone(), &opts, &cache).is_err()
+        );
+    }
+    assert_eq!(cache.misses(), 6);
+
+    // The least recently used entry is evicted once the cache is full.
+    assert!(cache.len() <= cache.capacity());
+    let mut other = flatbuffers::FlatBufferBuilder::new();
+    let name = other.create_string("Orc");
+    let orc = Monster::create(
+        &mut other,
+        &MonsterArgs {
+            hp: 300,
+            name: Some(name),
+            ..Default::default()
+        },
+    );
+    other.finish(orc, None);
+    let orc_data = other.finished_data().to_vec();
+    cache.clear();
+    FlatbufferRetained::<Monster>::new_cached(data.clone(), &opts, &cache).unwrap();
+    FlatbufferRetained::<Monster>::new_cached(orc_data.clone(), &opts, &cache).unwrap();
+    FlatbufferRetained::<Monster>::new_cached(data.clone(), &opts, &cache).unwrap();
+    let third = FlatbufferRetained::<Monster>::new_cached(
+        data.clone(),
+        &flatbuffers::VerifierOptions {
+            max_depth: 32,
+            ..Default::default()
+        },
+        &cache,
+    )
+    .unwrap();
+    assert_eq!(third.get().hp(), 80);
+    assert_eq!(cache.len(), 2);
+    assert_eq!((cache.hits(), cache.misses()), (1, 3));
+    FlatbufferRetained::<Monster>::new_cached(orc_data, &opts, &cache).unwrap();
+    assert_eq!(cache.misses(), 4);
+    FlatbufferRetained::<Monster>::new_cached(data, &opts, &cache).unwrap();
+    assert_eq!(cache.misses(), 5);
+}
+
+/// Storage whose contents can be swapped after verification, standing
+/// in for memory changed behind the retained value's back.
+#[cfg(feature = "checksum")]
+struct CorruptibleStorage {
+    original: Vec<u8>,
+    corrupted: Vec<u8>,
+    corrupt: std::cell::Cell<bool>,
+}
+
+#[cfg(feature = "checksum")]
+impl AsRef<[u8]> for CorruptibleStorage {
+    fn as_ref(&self) -> &[u8] {
+        if self.corrupt.get() {
+            &self.corrupted
+        } else {
+            &self.original
+        }
+    }
+}
+
+// Safety: this deliberately breaks the StableBytes contract to stand in
+// for memory corruption, which checksums exist to catch. The swap keeps
+// the buffer verifiable, so reading it stays sound.
+#[cfg(feature = "checksum")]
+unsafe impl flatbuffers_retained::StableBytes for CorruptibleStorage {}
+
+#[cfg(feature = "checksum")]
+#[test]
+fn test_integrity_checksum() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    build_armed_monster(&mut builder);
+    let original = builder.finished_data().to_vec();
+    let mut corrupted = original.clone();
+    // Change one byte of the "Axe" name, which keeps the buffer verifiable.
+    let name_at = corrupted
+        .windows(3)
+        .position(|window| window == b"Axe")
+        .unwrap();
+    corrupted[name_at] = b'W';
+
+    let unchecked = FlatbufferRetained::<Monster>::new(original.clone()).unwrap();
+    assert_eq!(unchecked.checksum(), None);
+    assert_eq!(unchecked.verify_integrity(), Ok(()));
+
+    let monster = FlatbufferRetained::<Monster, _>::new(CorruptibleStorage {
+        original: original.clone(),
+        corrupted,
+        corrupt: std::cell::Cell::new(false),
+    })
+    .unwrap()
+    .with_checksum();
+    assert_eq!(monster.checksum(), Some(crc32fast::hash(&original)));
+    assert_eq!(monster.verify_integrity(), Ok(()));
+    assert_eq!(monster.get().hp(), 80);
+
+    monster.storage().corrupt.set(true);
+    let err = monster.verify_integrity().unwrap_err();
+    assert!(matches!(
+        err,
+        flatbuffers_retained::Error::ChecksumMismatch { expected, found }
+            if expected == crc32fast::hash(&original) && found != expected
+    ));
+    #[cfg(debug_assertions)]
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| monster.get().hp())).is_err());
+
+    let retained = flatbuffers_retained::Retained::<Monster>::new_unprefixed(original)
+        .unwrap()
+        .with_checksum();
+    assert!(retained.checksum().is_some());
+    assert_eq!(retained.verify_integrity(), Ok(()));
+}
+
+#[cfg(feature = "envelope")]
+#[test]
+fn test_envelope() {
+    use flatbuffers_retained::{EnvelopeKey, Error, ENVELOPE_HEADER_LEN};
+    let key = EnvelopeKey::new(b"local secret", [7; 32]);
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    build_armed_monster(&mut builder);
+    let monster = FlatbufferRetained::<Monster>::new(builder.finished_data().to_vec()).unwrap();
+    let envelope = monster.to_envelope(&key);
+    assert_eq!(&envelope[ENVELOPE_HEADER_LEN..], &monster[..]);
+
+    // An authentic envelope skips the verifier, so even options that
+    // reject every table do not stop it from loading.
+    let no_tables = flatbuffers::VerifierOptions {
+        max_tables: 0,
+        ..Default::default()
+    };
+    let loaded = FlatbufferRetained::<Monster>::from_envelope_with_options(
+        envelope.clone(),
+        &key,
+        &no_tables,
+    )
+    .unwrap();
+    assert_eq!(loaded.head(), ENVELOPE_HEADER_LEN);
+    assert_eq!(loaded.get().hp(), 80);
+    assert_eq!(loaded.header(), &envelope[..ENVELOPE_HEADER_LEN]);
+
+    // Any other key, schema or payload falls back to the verifier.
+    let too_many_tables = Err(Error::InvalidFlatbuffer(
+        flatbuffers::InvalidFlatbuffer::TooManyTables,
+    ));
+    let other_key = EnvelopeKey::new(b"other secret", [7; 32]);
+    let other_schema = EnvelopeKey::new(b"local secret", [8; 32]);
+    for key in [&other_key, &other_schema] {
+        assert_eq!(
+            FlatbufferRetained::<Monster, &[u8]>::from_envelope_with_options(
+                &envelope, key, &no_tables
+            )
+            .map(|_| false),
+            too_many_tables
+        );
+        let loaded = FlatbufferRetained::<Monster, &[u8]>::from_envelope(&envelope, key).unwrap();
+        assert_eq!(loaded.get().hp(), 80);
+    }
+    let mut tampered = envelope.clone();
+    let name_at = tampered
+        .windows(3)
+        .position(|window| window == b"Axe")
+        .unwrap();
+    tampered[name_at] = b'W';
+    assert_eq!(
+        FlatbufferRetained::<Monster>::from_envelope_with_options(tampered, &key, &no_tables)
+            .map(|_| false),
+        too_many_tables
+    );
+    assert!(flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::from_envelope_with_options(
+        envelope.clone(),
+        &key,
+        &no_tables
+    )
+    .is_err());
+
+    assert_eq!(
+        FlatbufferRetained::<Monster>::from_envelope(envelope[..40].to_vec(), &key).map(|_| false),
+        Err(Error::MalformedEnvelope)
+    );
+    assert_eq!(
+        FlatbufferRetained::<Monster>::from_envelope(monster.into(), &key).map(|_| false),
+        Err(Error::MalformedEnvelope)
+    );
+
+    let mut prefixed = (builder.finished_data().len() as u32)
+        .to_le_bytes()
+        .to_vec();
+    prefixed.extend_from_slice(builder.finished_data());
+    let monster =
+        flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::new(prefixed).unwrap();
+    let loaded = flatbuffers_retained::SizePrefixedFlatbufferRetained::<Monster>::from_envelope_with_options(
+        monster.to_envelope(&key),
+        &key,
+        &no_tables,
+    )
+    .unwrap();
+    assert_eq!(loaded.get().hp(), 80);
+}
+
+#[cfg(any(feature = "zstd", feature = "lz4"))]
+#[test]
+fn test_compressed_frames() {
+    use flatbuffers_retained::{Error, SizePrefixedFlatbufferRetained};
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    build_armed_monster(&mut builder);
+    let monster = FlatbufferRetained::<Monster>::new(builder.finished_data().to_vec()).unwrap();
+    let mut prefixed = (monster.len() as u32).to_le_bytes().to_vec();
+    prefixed.extend_from_slice(&monster);
+    let prefixed = SizePrefixedFlatbufferRetained::<Monster>::new(prefixed).unwrap();
+    let no_tables = flatbuffers::VerifierOptions {
+        max_tables: 0,
+        ..Default::default()
+    };
+
+    #[cfg(feature = "zstd")]
+    {
+        let frame = monster.to_zstd(3).unwrap();
+        let loaded = FlatbufferRetained::<Monster>::from_zstd(&frame, 1 << 20).unwrap();
+        assert_eq!(&loaded[..], &monster[..]);
+        assert_eq!(loaded.get().hp(), 80);
+        assert_eq!(
+            FlatbufferRetained::<Monster>::from_zstd(&frame, monster.len() - 1).map(|_| false),
+            Err(Error::DecompressedTooLarge {
+                limit: monster.len() - 1
+            })
+        );
+        assert_eq!(
+            FlatbufferRetained::<Monster>::from_zstd_with_options(&frame, 1 << 20, &no_tables)
+                .map(|_| false),
+            Err(Error::InvalidFlatbuffer(
+                flatbuffers::InvalidFlatbuffer::TooManyTables
+            ))
+        );
+        // A frame asking for a window beyond the limit is refused up front.
+        let bomb = zstd::stream::encode_all(&vec![0u8; 1 << 24][..], 19).unwrap();
+        assert!(bomb.len() < 4096);
+        assert!(matches!(
+            FlatbufferRetained::<Monster>::from_zstd(&bomb, 1 << 20),
+            Err(Error::Decompression(_))
+        ));
+        // A frame with a small window is cut off once it exceeds the limit.
+        let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), 19).unwrap();
+        encoder.window_log(10).unwrap();
+        std::io::Write::write_all(&mut encoder, &vec![0u8; 1 << 24]).unwrap();
+        let bomb = encoder.finish().unwrap();
+        assert!(bomb.len() < 1 << 17);
+        assert_eq!(
+            FlatbufferRetained::<Monster>::from_zstd(&bomb, 1 << 20).map(|_| false),
+            Err(Error::DecompressedTooLarge { limit: 1 << 20 })
+        );
+        assert!(matches!(
+            FlatbufferRetained::<Monster>::from_zstd(&monster, 1 << 20),
+            Err(Error::Decompression(_))
+        ));
+        let loaded = SizePrefixedFlatbufferRetained::<Monster>::from_zstd(
+            &prefixed.to_zstd(1).unwrap(),
+            1 << 20,
+        )
+        .unwrap();
+        assert_eq!(loaded.get().hp(), 80);
+    }
+
+    #[cfg(feature = "lz4")]
+    {
+        let frame = monster.to_lz4();
+        let loaded =
+            FlatbufferRetained::<Monster, std::sync::Arc<[u8]>>::from_lz4(&frame, 1 << 20).unwrap();
+        assert_eq!(&loaded[..], &monster[..]);
+        assert_eq!(
+            FlatbufferRetained::<Monster>::from_lz4(&frame, monster.len() - 1).map(|_| false),
+            Err(Error::DecompressedTooLarge {
+                limit: monster.len() - 1
+            })
+        );
+        assert_eq!(
+            FlatbufferRetained::<Monster>::from_lz4_with_options(&frame, 1 << 20, &no_tables)
+                .map(|_| false),
+            Err(Error::InvalidFlatbuffer(
+                flatbuffers::InvalidFlatbuffer::TooManyTables
+            ))
+        );
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        std::io::Write::write_all(&mut encoder, &vec![0u8; 1 << 24]).unwrap();
+        let bomb = encoder.finish().unwrap();
+        assert!(bomb.len() < 1 << 17);
+        assert_eq!(
+            FlatbufferRetained::<Monster>::from_lz4(&bomb, 1 << 20).map(|_| false),
+            Err(Error::DecompressedTooLarge { limit: 1 << 20 })
+        );
+        assert!(matches!(
+            FlatbufferRetained::<Monster>::from_lz4(&monster, 1 << 20),
+            Err(Error::Decompression(_))
+        ));
+        let loaded =
+            SizePrefixedFlatbufferRetained::<Monster>::from_lz4(&prefixed.to_lz4(), 1 << 20)
+                .unwrap();
+        assert_eq!(loaded.get().hp(), 80);
+    }
+}
+
+#[test]
+fn test_strict_lengths() {
+    use flatbuffers_retained::{Error, SizePrefixedFlatbufferRetained};
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    build_armed_monster(&mut builder);
+    let data = builder.finished_data().to_vec();
+    let monster = FlatbufferRetained::<Monster>::new_strict(data.clone()).unwrap();
+    assert_eq!(monster.get().hp(), 80);
+
+    let mut garbage = data.clone();
+    garbage.push(0xff);
+    assert!(FlatbufferRetained::<Monster>::new(garbage.clone()).is_ok());
+    assert!(matches!(
+        FlatbufferRetained::<Monster>::new_strict(garbage),
+        Err(Error::TrailingBytes { len, .. }) if len == data.len() + 1
+    ));
+    let mut zeros = data.clone();
+    zeros.extend_from_slice(&[0; 32]);
+    assert!(matches!(
+        FlatbufferRetained::<Monster>::new_strict(zeros),
+        Err(Error::TrailingBytes { .. })
+    ));
+    assert_eq!(
+        FlatbufferRetained::<Monster>::new_strict(data[..data.len() - 8].to_vec()).map(|_| false),
+        Err(Error::InvalidFlatbuffer(
+            FlatbufferRetained::<Monster>::new(data[..data.len() - 8].to_vec())
+                .map(|_| false)
+                .unwrap_err()
+        ))
+    );
+
+    let mut prefixed = (data.len() as u32).to_le_bytes().to_vec();
+    prefixed.extend_from_slice(&data);
+    let monster = SizePrefixedFlatbufferRetained::<Monster>::new_strict(prefixed.clone()).unwrap();
+    assert_eq!(monster.get().hp(), 80);
+
+    let mut wrong_size = prefixed.clone();
+    wrong_size[..4].copy_from_slice(&(data.len() as u32 + 4).to_le_bytes());
+    assert!(SizePrefixedFlatbufferRetained::<Monster>::new(wrong_size.clone()).is_ok());
+    assert_eq!(
+        SizePrefixedFlatbufferRetained::<Monster>::new_strict(wrong_size.clone()).map(|_| false),
+        Err(Error::SizePrefixMismatch {
+            declared: data.len() + 4,
+            actual: data.len()
+        })
+    );
+    assert_eq!(
+        SizePrefixedFlatbufferRetained::<Monster>::new_declared(wrong_size).map(|_| false),
+        Err(Error::SizePrefixMismatch {
+            declared: data.len() + 4,
+            actual: data.len()
+        })
+    );
+    assert!(matches!(
+        SizePrefixedFlatbufferRetained::<Monster>::new_strict(vec![1, 0]),
+        Err(Error::InvalidFlatbuffer(_))
+    ));
+    assert_eq!(
+        SizePrefixedFlatbufferRetained::<Monster>::new_declared(vec![1, 0]).map(|_| false),
+        Err(Error::SizePrefixMismatch {
+            declared: 0,
+            actual: 0
+        })
+    );
+
+    // Only the declared region is verified and exposed.
+    let mut framed = prefixed.clone();
+    framed.extend_from_slice(b"next frame");
+    assert!(matches!(
+        SizePrefixedFlatbufferRetained::<Monster>::new_strict(framed.clone()),
+        Err(Error::SizePrefixMismatch { .. })
+    ));
+    let monster = SizePrefixedFlatbufferRetained::<Monster>::new_declared(framed).unwrap();
+    assert_eq!(monster.get().hp(), 80);
+    assert_eq!(&monster[..], &prefixed[..]);
+    assert_eq!(monster.trailer(), b"next frame");
+
+    // A string only a newer schema reaches is written first, so it sits
+    // past everything the verifier reads. Only the size prefix keeps it.
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let extra = builder.create_string("from a newer schema");
+    let start = builder.start_table();
+    builder.push_slot::<i16>(Monster::VT_HP, 300, 100);
+    builder.push_slot_always(Monster::VT_PATH + 2, extra);
+    let orc = builder.end_table(start);
+    builder.finish(flatbuffers::WIPOffset::<Monster>::new(orc.value()), None);
+    let newer = builder.finished_data().to_vec();
+    assert!(FlatbufferRetained::<Monster>::new(newer.clone()).is_ok());
+    assert_eq!(
+        FlatbufferRetained::<Monster>::new_strict(newer.clone()).map(|_| false),
+        Err(Error::TrailingBytes {
+            extent: 44,
+            len: 68
+        })
+    );
+    let mut prefixed = (newer.len() as u32).to_le_bytes().to_vec();
+    prefixed.extend_from_slice(&newer);
+    let monster = SizePrefixedFlatbufferRetained::<Monster>::new_strict(prefixed).unwrap();
+    assert_eq!(monster.get().hp(), 300);
+}
+
+#[test]
+fn test_trim() {
+    use flatbuffers_retained::{Retained, SizePrefixedFlatbufferRetained, TrimReport};
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    build_armed_monster(&mut builder);
+    let finished = builder.finished_data().to_vec();
+    let monster = FlatbufferRetained::<Monster>::from_builder(builder).unwrap();
+    assert!(monster.head() > 0);
+
+    // Builder slack in front of the flatbuffer goes.
+    let report = monster.trim_report();
+    assert_eq!(
+        report,
+        TrimReport {
+            before: monster.storage().len(),
+            after: finished.len()
+        }
+    );
+    assert_eq!(report.saved(), monster.head());
+    let trimmed = monster.trim();
+    assert_eq!(trimmed.head(), 0);
+    assert_eq!(trimmed.storage(), &finished);
+    assert_eq!(trimmed.get().hp(), 80);
+    assert_eq!(trimmed.trim_report().saved(), 0);
+
+    // A trailer after the declared size goes, the prefix stays as it is.
+    let mut prefixed = (finished.len() as u32).to_le_bytes().to_vec();
+    prefixed.extend_from_slice(&finished);
+    let framed = prefixed.clone();
+    prefixed.extend_from_slice(b"next frame");
+    let monster = SizePrefixedFlatbufferRetained::<Monster>::new_declared(prefixed).unwrap();
+    assert_eq!(monster.trim_report().saved(), 10);
+    let trimmed = monster.trim();
+    assert_eq!(trimmed.storage(), &framed);
+    assert_eq!(trimmed.get().hp(), 80);
+
+    let retained = Retained::<Monster>::from(monster);
+    assert_eq!(retained.trim_report().saved(), 10);
+    assert_eq!(retained.trim().get().hp(), 80);
+
+    // Bytes only a newer schema reaches are kept.
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let extra = builder.create_string("from a newer schema");
+    let name = builder.create_string("Orc");
+    builder.create_string(&"unreachable".repeat(20));
+    let start = builder.start_table();
+    builder.push_slot::<i16>(Monster::VT_HP, 300, 100);
+    builder.push_slot_always(Monster::VT_NAME, name);
+    builder.push_slot_always(Monster::VT_PATH + 2, extra);
+    let orc = builder.end_table(start);
+    builder.finish(flatbuffers::WIPOffset::<Monster>::new(orc.value()), None);
+    let finished = builder.finished_data().to_vec();
+    let monster = FlatbufferRetained::<Monster>::new(finished.clone()).unwrap();
+    let trimmed = monster.trim();
+    assert_eq!(trimmed.storage(), &finished);
+    // Safety: the field was written as a string above.
+    let extra = unsafe {
+        trimmed
+            .get()
+            ._tab
+            .get::<flatbuffers::ForwardsUOffset<&str>>(Monster::VT_PATH + 2, None)
+    };
+    assert_eq!(extra, Some("from a newer schema"));
+
+    // Rebuilding drops regions that are unreachable from the root,
+    // along with fields T does not know about.
+    let rebuilt = monster
+        .rebuild(|monster, builder| {
+            let monster = monster.get();
+            let name = monster.name().map(|name| builder.create_string(name));
+            let orc = Monster::create(
+                builder,
+                &MonsterArgs {
+                    hp: monster.hp(),
+                    name,
+                    ..Default::default()
+                },
+            );
+            builder.finish(orc, None);
+        })
+        .unwrap();
+    assert_eq!(rebuilt.storage().len(), rebuilt.len());
+    assert!(rebuilt.storage().len() + 200 < monster.storage().len());
+    assert_eq!(rebuilt.get().hp(), 300);
+    assert_eq!(rebuilt.get().name(), Some("Orc"));
+}
+
+/// A policy that every monster handed to game logic must satisfy.
+struct Playable;
+
+impl<'x> flatbuffers_retained::Validator<Monster<'x>> for Playable {
+    type Error = String;
+    fn validate(monster: &Monster<'_>) -> Result<(), String> {
+        if monster.hp() < 0 {
+            return Err(format!("negative hp {}", monster.hp()));
+        }
+        match monster.weapons() {
+            Some(weapons) if weapons.len() > 64 => Err("too many weapons".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Game logic that relies on the Playable policy without checking it.
+fn total_damage(monster: &flatbuffers_retained::Validated<Monster<'static>, Playable>) -> i16 {
+    monster
+        .get()
+        .weapons()
+        .map(|weapons| weapons.iter().map(|weapon| weapon.damage()).sum())
+        .unwrap_or(0)
+}
+
+#[test]
+fn test_validated_policy() {
+    use flatbuffers_retained::{Retained, Validated, ValidationError};
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    build_armed_monster(&mut builder);
+    let data = builder.finished_data().to_vec();
+    let monster = Validated::<Monster, Playable>::new_unprefixed(data.clone()).unwrap();
+    assert_eq!(total_damage(&monster), 8);
+    assert_eq!(monster.get().hp(), 80);
+    assert_eq!(monster.clone().into_retained().get().hp(), 80);
+
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let ghost = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: -5,
+            ..Default::default()
+        },
+    );
+    builder.finish(ghost, None);
+    let ghost = builder.finished_data().to_vec();
+    assert_eq!(
+        Validated::<Monster, Playable>::new_unprefixed(ghost.clone()).map(|_| false),
+        Err(ValidationError::Policy("negative hp -5".to_string()))
+    );
+    assert!(matches!(
+        Validated::<Monster, Playable>::new_unprefixed(data[..data.len() / 2].to_vec()),
+        Err(ValidationError::InvalidFlatbuffer(_))
+    ));
+
+    let retained: Retained<Monster> = FlatbufferRetained::<Monster>::new(ghost).unwrap().into();
+    let rejected = Validated::<Monster, Playable>::from_retained(retained).unwrap_err();
+    assert_eq!(rejected.error(), "negative hp -5");
+    assert_eq!(rejected.into_inner().get().hp(), -5);
+
+    let mut prefixed = (data.len() as u32).to_le_bytes().to_vec();
+    prefixed.extend_from_slice(&data);
+    let monster = Validated::<Monster, Playable>::new_size_prefixed(prefixed).unwrap();
+    assert_eq!(total_damage(&monster), 8);
+}
+
+const WEAPON_IDENTIFIER: &str = "WEAP";
+
+flatbuffers_retained::retained_dispatch! {
+    /// Every message the test bus carries.
+    #[derive(Debug)]
+    enum Message {
+        Monster(Monster) = "MONS",
+        Weapon(Weapon) = WEAPON_IDENTIFIER,
+    }
+}
+
+#[test]
+fn test_dispatch_by_identifier() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let name = builder.create_string("Orc");
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 300,
+            name: Some(name),
+            ..Default::default()
+        },
+    );
+    builder.finish(orc, Some("MONS"));
+    let monster = builder.finished_data().to_vec();
+
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let name = builder.create_string("Sword");
+    let sword = Weapon::create(
+        &mut builder,
+        &WeaponArgs {
+            name: Some(name),
+            damage: 3,
+        },
+    );
+    builder.finish_size_prefixed(sword, Some(WEAPON_IDENTIFIER));
+    let weapon = builder.finished_data().to_vec();
+
+    match Message::from_bytes(monster.clone()).unwrap() {
+        Message::Monster(monster) => assert_eq!(monster.get().hp(), 300),
+        other => panic!("dispatched to {:?}", other),
+    }
+    match Message::<&[u8]>::from_size_prefixed_bytes(&weapon).unwrap() {
+        Message::Weapon(weapon) => assert_eq!(weapon.get().damage(), 3),
+        other => panic!("dispatched to {:?}", other),
+    }
+    assert_eq!(
+        Message::from_bytes(monster.clone()).unwrap().identifier(),
+        "MONS"
+    );
+
+    // The identifier picks the variant, but the verifier still runs.
+    let no_tables = flatbuffers::VerifierOptions {
+        max_tables: 0,
+        ..Default::default()
+    };
+    assert_eq!(
+        Message::from_bytes_with_options(monster.clone(), &no_tables).map(|_| false),
+        Err(flatbuffers_retained::Error::InvalidFlatbuffer(
+            flatbuffers::InvalidFlatbuffer::TooManyTables
+        ))
+    );
+    assert_eq!(
+        Message::from_bytes(weapon.clone()).map(|_| false),
+        Err(flatbuffers_retained::Error::UnknownIdentifier {
+            found: Some(weapon[4..8].try_into().unwrap())
+        })
+    );
+    let mut unknown = monster.clone();
+    unknown[4..8].copy_from_slice(b"NOPE");
+    assert_eq!(
+        Message::from_bytes(unknown).map(|_| false),
+        Err(flatbuffers_retained::Error::UnknownIdentifier {
+            found: Some(*b"NOPE")
+        })
+    );
+    assert_eq!(
+        Message::from_bytes(vec![0; 6]).map(|_| false),
+        Err(flatbuffers_retained::Error::UnknownIdentifier { found: None })
+    );
+}
+
+flatbuffers_retained::retained_type! {
+    /// A size-prefixed monster as read off the wire.
+    #[derive(Clone, Debug)]
+    pub struct FramedMonster(size_prefixed Monster) {
+        /// The monster's remaining health.
+        pub fn hp() -> i16 => hp;
+        #[must_use]
+        pub fn name() -> Option<&str> => name;
+        // Attributes reach the accessor, so this one is never compiled.
+        #[cfg(any())]
+        pub fn missing() -> i16 => no_such_field;
+    }
+}
+
+#[test]
+fn test_retained_type() {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let name = builder.create_string("Orc");
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 300,
+            name: Some(name),
+            ..Default::default()
+        },
+    );
+    builder.finish_size_prefixed(orc, None);
+    let data = builder.finished_data().to_vec();
+
+    let monster = FramedMonster::new(data.clone()).unwrap();
+    assert_eq!(monster.hp(), 300);
+    assert_eq!(monster.name(), Some("Orc"));
+    assert_eq!(monster.get().hp(), 300);
+    assert_eq!(&monster[..], &data[..]);
+    assert_eq!(monster.inner().get().hp(), 300);
+    let cloned = monster.clone();
+    assert_eq!(Vec::<u8>::from(monster), data);
+
+    let borrowed = FramedMonster::<&[u8]>::new(&data).unwrap();
+    assert_eq!(borrowed.name(), Some("Orc"));
+    let bytes: &[u8] = std::borrow::Borrow::borrow(&borrowed);
+    assert_eq!(bytes, &data[..]);
+    assert_eq!(borrowed.into_inner().get().hp(), 300);
+
+    assert!(FramedMonster::try_from(data.clone()).is_ok());
+    let rejected = FramedMonster::try_from(data[..data.len() / 2].to_vec()).unwrap_err();
+    assert_eq!(rejected.into_inner(), data[..data.len() / 2]);
+    assert!(FramedMonster::new_with_options(
+        data,
+        &flatbuffers::VerifierOptions {
+            max_tables: 0,
+            ..Default::default()
+        }
+    )
+    .is_err());
+    let retained: flatbuffers_retained::SizePrefixedFlatbufferRetained<Monster<'static>> =
+        cloned.into_inner();
+    let wrapped: FramedMonster = retained.into();
+    assert_eq!(wrapped.hp(), 300);
+}
+
+#[test]
+fn test_retained_builder() {
+    use flatbuffers::WIPOffset;
+    use flatbuffers_retained::{RetainedBuilder, SizePrefixedFlatbufferRetained};
+    let mut builder: RetainedBuilder<Monster> = RetainedBuilder::with_capacity(1024);
+    for hp in 0..16 {
+        let name = builder.create_string("Orc");
+        let orc = Monster::create(
+            &mut builder,
+            &MonsterArgs {
+                hp,
+                name: Some(name),
+                ..Default::default()
+            },
+        );
+        let monster = builder.finish(orc, Some("MONS")).unwrap();
+        // The output is sized to the flatbuffer, the builder keeps its buffer.
+        assert_eq!(monster.head(), 0);
+        assert!(monster.storage().len() < 1024);
+        assert!(flatbuffers::buffer_has_identifier(&monster, "MONS", false));
+        assert_eq!(monster.get().hp(), hp);
+        assert_eq!(monster.get().name(), Some("Orc"));
+    }
+
+    let orc = Monster::create(
+        &mut builder,
+        &MonsterArgs {
+            hp: 80,
+            ..Default::default()
+        },
+    );
+    let monster: SizePrefixedFlatbufferRetained<Monster> =
+        builder.finish_size_prefixed(orc, None).unwrap();
+    assert_eq!(monster.get().hp(), 80);
+    // Safety: the root was returned by Monster::create on this builder.
+    let monster = unsafe {
+        let orc = Monster::create(&mut builder, &MonsterArgs::default());
+        builder.finish_unchecked(orc, None)
+    };
+    assert_eq!(monster.get().hp(), 100);
+
+    // A made up offset is caught by the verifier and the builder stays usable.
+    builder.create_string("not a monster");
+    assert!(builder.finish(WIPOffset::new(4), None).is_err());
+    let orc = Monster::create(&mut builder, &MonsterArgs::default());
+    assert_eq!(builder.finish(orc, None).unwrap().get().hp(), 100);
+    assert_eq!(builder.into_inner().collapse().0.len(), 1024);
+}